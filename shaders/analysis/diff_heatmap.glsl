@@ -0,0 +1,41 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "../inc/srgb.hlsl"
+
+// Must match `DiffMetric` in `src/image_diff.rs`
+#define DIFF_METRIC_CIEDE2000 0
+#define DIFF_METRIC_ITP 1
+
+// ΔE at which the heatmap saturates to white.
+#define DIFF_HEATMAP_MAX_DELTA_E 10.0
+
+// Output of `image_diff.glsl`, the same size as the framebuffer.
+uniform sampler2D diff_texture;
+uniform int diff_metric;
+
+out float4 output_rgba;
+
+// Black through blue, red, and yellow, to white.
+float3 heatmap(float t) {
+    const float3 stops[5] = float3[](
+        float3(0.0, 0.0, 0.0),
+        float3(0.1, 0.1, 0.8),
+        float3(0.9, 0.1, 0.1),
+        float3(1.0, 0.9, 0.1),
+        float3(1.0, 1.0, 1.0)
+    );
+
+    const float x = saturate(t) * 4.0;
+    const int i = min(int(x), 3);
+    return lerp(stops[i], stops[i + 1], x - i);
+}
+
+float4 draw_heatmap() {
+    const float2 delta_e = texelFetch(diff_texture, ivec2(gl_FragCoord.xy), 0).rg;
+    const float value = diff_metric == DIFF_METRIC_ITP ? delta_e.y : delta_e.x;
+
+    // The framebuffer is sRGB; the stops above are perceptual.
+    return float4(sRGB_EOTF(heatmap(value / DIFF_HEATMAP_MAX_DELTA_E)), 1.0);
+}
+
+#define SHADER_MAIN_FN output_rgba = draw_heatmap();
@@ -0,0 +1,25 @@
+#ifndef NOTORIOUS6_HUE_ANALYSIS_COMMON_GLSL
+#define NOTORIOUS6_HUE_ANALYSIS_COMMON_GLSL
+
+// Must match the `HUE_ANALYSIS_*` constants in `src/hue_analysis.rs`
+#define HUE_ANALYSIS_HUE_COUNT 36
+#define HUE_ANALYSIS_EV_COUNT 25
+#define HUE_ANALYSIS_EV_MIN -4.0
+#define HUE_ANALYSIS_EV_STEP 0.5
+#define HUE_ANALYSIS_VALUES_PER_CELL 7
+
+// Lightness and chroma of the grid colors at EV 0, before normalizing to mid-gray luminance.
+#define HUE_ANALYSIS_OKLAB_L 0.7
+#define HUE_ANALYSIS_OKLAB_CHROMA 0.12
+
+// Per cell: input hue in Oklab, ICtCp, and IPT, output hue in the same, and output Oklab chroma.
+// Hues are in degrees.
+layout(std430, binding = 0) buffer hue_analysis_buffer {
+    float hue_analysis_results[];
+};
+
+float hue_analysis_ev(int ev_index) {
+    return HUE_ANALYSIS_EV_MIN + ev_index * HUE_ANALYSIS_EV_STEP;
+}
+
+#endif  // NOTORIOUS6_HUE_ANALYSIS_COMMON_GLSL
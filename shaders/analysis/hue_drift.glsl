@@ -0,0 +1,57 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "../inc/math.hlsl"
+#include "../inc/srgb.hlsl"
+#include "../inc/oklab.hlsl"
+#include "../inc/ictcp.hlsl"
+#include "../inc/ipt.hlsl"
+#include "hue_analysis_common.glsl"
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+// The grid from `hue_grid.glsl`, and the technique applied to it.
+uniform sampler2D input_texture;
+uniform sampler2D output_texture;
+
+// Hue angles in Oklab, ICtCp, and IPT, in degrees.
+float3 hues(float3 rgb) {
+    const float3 oklab = sRGB_to_Oklab(rgb);
+    // 1.0 as 100 nits.
+    const float3 ictcp = BT709_to_ICtCp(rgb * 100.0);
+    const float3 ipt = XYZ_to_IPT(sRGB_to_XYZ(rgb));
+
+    return degrees(float3(
+        atan(oklab.z, oklab.y),
+        atan(ictcp.z, ictcp.y),
+        atan(ipt.z, ipt.y)
+    ));
+}
+
+void main() {
+    const ivec2 px = ivec2(gl_GlobalInvocationID.xy);
+    if (px.x >= HUE_ANALYSIS_HUE_COUNT || px.y >= HUE_ANALYSIS_EV_COUNT) {
+        return;
+    }
+
+    const float3 input_rgb = texelFetch(input_texture, px, 0).rgb;
+
+    // The technique's output is flipped vertically with respect to its input.
+    const float3 output_rgb = max(0.0.xxx, texelFetch(
+        output_texture,
+        ivec2(px.x, HUE_ANALYSIS_EV_COUNT - 1 - px.y),
+        0
+    ).rgb);
+
+    const float3 input_hues = hues(input_rgb);
+    const float3 output_hues = hues(output_rgb);
+    const float output_chroma = length(sRGB_to_Oklab(output_rgb).yz);
+
+    const int offset = (px.y * HUE_ANALYSIS_HUE_COUNT + px.x) * HUE_ANALYSIS_VALUES_PER_CELL;
+    hue_analysis_results[offset + 0] = input_hues.x;
+    hue_analysis_results[offset + 1] = input_hues.y;
+    hue_analysis_results[offset + 2] = input_hues.z;
+    hue_analysis_results[offset + 3] = output_hues.x;
+    hue_analysis_results[offset + 4] = output_hues.y;
+    hue_analysis_results[offset + 5] = output_hues.z;
+    hue_analysis_results[offset + 6] = output_chroma;
+}
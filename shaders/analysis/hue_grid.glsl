@@ -0,0 +1,29 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "../inc/math.hlsl"
+#include "../inc/srgb.hlsl"
+#include "../inc/oklab.hlsl"
+#include "hue_analysis_common.glsl"
+
+layout(local_size_x = 8, local_size_y = 8) in;
+layout(rgba32f) uniform image2D output_image;
+
+// Hue along x, exposure along y.
+void main() {
+    const ivec2 px = ivec2(gl_GlobalInvocationID.xy);
+    if (px.x >= HUE_ANALYSIS_HUE_COUNT || px.y >= HUE_ANALYSIS_EV_COUNT) {
+        return;
+    }
+
+    const float angle = 2.0 * M_PI * px.x / HUE_ANALYSIS_HUE_COUNT;
+    float3 rgb = max(0.0.xxx, Oklab_to_sRGB(float3(
+        HUE_ANALYSIS_OKLAB_L,
+        HUE_ANALYSIS_OKLAB_CHROMA * cos(angle),
+        HUE_ANALYSIS_OKLAB_CHROMA * sin(angle)
+    )));
+
+    rgb *= 0.18 / sRGB_to_luminance(rgb);
+    rgb *= exp2(hue_analysis_ev(px.y));
+
+    imageStore(output_image, px, float4(rgb, 1.0));
+}
@@ -0,0 +1,45 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "../inc/math.hlsl"
+#include "../inc/srgb.hlsl"
+#include "../inc/lab.hlsl"
+#include "../inc/ictcp.hlsl"
+#include "../inc/delta_e.hlsl"
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+// Display-linear outputs of the two techniques being compared.
+uniform sampler2D image_a;
+uniform sampler2D image_b;
+// Set when `image_b` is a reference image, which is stored top row first,
+// unlike the framebuffer `image_a` is rendered to.
+uniform int flip_image_b;
+
+// ΔE 2000 in red, ΔE-ITP in green.
+layout(rgba32f) uniform image2D output_image;
+
+// Display white, in nits.
+#define DIFF_WHITE_NITS 100.0
+
+void main() {
+    const ivec2 px = ivec2(gl_GlobalInvocationID.xy);
+    const ivec2 size = textureSize(image_a, 0);
+    if (any(greaterThanEqual(px, size))) {
+        return;
+    }
+
+    const ivec2 px_b = flip_image_b != 0 ? ivec2(px.x, size.y - 1 - px.y) : px;
+    const float3 rgb_a = max(0.0.xxx, texelFetch(image_a, px, 0).rgb);
+    const float3 rgb_b = max(0.0.xxx, texelFetch(image_b, px_b, 0).rgb);
+
+    const float de_2000 = delta_e_2000(
+        XYZ_to_LAB(sRGB_to_XYZ(rgb_a)),
+        XYZ_to_LAB(sRGB_to_XYZ(rgb_b))
+    );
+    const float de_itp = delta_e_itp(
+        BT709_to_ICtCp(rgb_a * DIFF_WHITE_NITS),
+        BT709_to_ICtCp(rgb_b * DIFF_WHITE_NITS)
+    );
+
+    imageStore(output_image, px, float4(de_2000, de_itp, 0.0, 1.0));
+}
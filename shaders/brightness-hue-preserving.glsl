@@ -96,6 +96,9 @@ bool is_inside_2d_gamut_slice(float2 pos, float corner_radius) {
 bool is_inside_target_gamut(float3 pos) {
 	const float3 rgb_corner_radii = GAMUT_CORNER_CUT_RADII;
 
+    // The target is the gamut of the display, which may be wider than BT.709.
+    pos = BT709_to_display(pos);
+
 	return true
 #if TRIM_GAMUT_CORNERS
         // Trim red except where green is high or blue is high
@@ -0,0 +1,67 @@
+#include "../inc/prelude.glsl"
+#include "../inc/srgb.hlsl"
+
+// Must match the domain mapping in `src/external_lut.rs`
+#define EXTERNAL_LUT_DOMAIN_LINEAR 0
+#define EXTERNAL_LUT_DOMAIN_LOG2 1
+#define EXTERNAL_LUT_DOMAIN_ACESCCT 2
+
+uniform sampler1D external_lut_1d;
+uniform sampler3D external_lut_3d;
+uniform int external_lut_has_1d;
+uniform int external_lut_has_3d;
+uniform int external_lut_size_1d;
+uniform int external_lut_size_3d;
+uniform int external_lut_domain;
+uniform float2 external_lut_log2_range;
+// [min, max] of the input of each table.
+uniform float3 external_lut_input_range_1d[2];
+uniform float3 external_lut_input_range_3d[2];
+
+float3 acescct_encode(float3 lin) {
+    return mix(
+        (log2(max(1e-10.xxx, lin)) + 9.72) / 17.52,
+        10.5402377416545 * lin + 0.0729055341958355,
+        lessThanEqual(lin, 0.0078125.xxx)
+    );
+}
+
+float3 external_lut_shaper(float3 stimulus) {
+    if (external_lut_domain == EXTERNAL_LUT_DOMAIN_LOG2) {
+        return (log2(max(1e-10.xxx, stimulus)) - external_lut_log2_range.x)
+            / (external_lut_log2_range.y - external_lut_log2_range.x);
+    } else if (external_lut_domain == EXTERNAL_LUT_DOMAIN_ACESCCT) {
+        return acescct_encode(stimulus);
+    } else {
+        return stimulus;
+    }
+}
+
+// Maps [0, 1] to the centers of the first and last texels.
+float3 lut_texel_coord(float3 t, int size) {
+    return (saturate(t) * (size - 1) + 0.5) / size;
+}
+
+float3 compress_stimulus(ShaderInput shader_input) {
+    float3 coord = external_lut_shaper(shader_input.stimulus);
+
+    if (external_lut_has_1d != 0) {
+        coord = (coord - external_lut_input_range_1d[0])
+            / (external_lut_input_range_1d[1] - external_lut_input_range_1d[0]);
+        const float3 lut_coord = lut_texel_coord(coord, external_lut_size_1d);
+        coord = float3(
+            textureLod(external_lut_1d, lut_coord.r, 0).r,
+            textureLod(external_lut_1d, lut_coord.g, 0).g,
+            textureLod(external_lut_1d, lut_coord.b, 0).b
+        );
+    }
+
+    if (external_lut_has_3d != 0) {
+        coord = (coord - external_lut_input_range_3d[0])
+            / (external_lut_input_range_3d[1] - external_lut_input_range_3d[0]);
+        coord = textureLod(external_lut_3d, lut_texel_coord(coord, external_lut_size_3d), 0).rgb;
+    }
+
+    // The pipeline expects linear output, and encodes it for display.
+    return sRGB_EOTF(coord);
+}
@@ -0,0 +1,61 @@
+#ifndef NOTORIOUS6_DEBUG_VIEW_GLSL
+#define NOTORIOUS6_DEBUG_VIEW_GLSL
+
+#include "srgb.hlsl"
+#include "output_target.glsl"
+
+// Must match `DebugView` in `src/debug_view.rs`
+#define DEBUG_VIEW_NONE 0
+#define DEBUG_VIEW_FALSE_COLOR 1
+#define DEBUG_VIEW_CLIPPED_OUTPUT 2
+#define DEBUG_VIEW_OUT_OF_GAMUT_INPUT 3
+
+uniform int debug_view;
+
+// One band per stop of exposure relative to mid-gray.
+float3 debug_false_color(float stops_from_mid_gray) {
+    const float3 palette[11] = float3[11](
+        float3(0.1, 0.0, 0.2),      // -5 and below
+        float3(0.25, 0.0, 0.5),     // -4
+        float3(0.0, 0.1, 0.8),      // -3
+        float3(0.0, 0.4, 0.6),      // -2
+        float3(0.1, 0.3, 0.1),      // -1
+        float3(0.18, 0.18, 0.18),   // mid-gray
+        float3(0.4, 0.8, 0.1),      // +1
+        float3(0.9, 0.9, 0.0),      // +2
+        float3(1.0, 0.5, 0.0),      // +3
+        float3(1.0, 0.0, 0.0),      // +4
+        float3(1.0, 1.0, 1.0)       // +5 and above
+    );
+
+    const int band = clamp(int(round(stops_from_mid_gray)) + 5, 0, 10);
+    return palette[band];
+}
+
+// Dimmed monochrome version of the compressed stimulus, to make highlights stand out.
+float3 debug_backdrop(float3 compressed) {
+    return 0.5 * sRGB_to_luminance(saturate(compressed)).xxx;
+}
+
+// `raw_stimulus` is the input before clamping to the BT.709 gamut,
+// and `compressed` is the result of `compress_stimulus`.
+float3 apply_debug_view(float3 raw_stimulus, float3 stimulus, float3 compressed) {
+    if (debug_view == DEBUG_VIEW_FALSE_COLOR) {
+        return debug_false_color(log2(max(1e-10, sRGB_to_luminance(stimulus)) / 0.18));
+    } else if (debug_view == DEBUG_VIEW_CLIPPED_OUTPUT) {
+        // Red where any channel is above the displayable range, blue where below, magenta for both.
+        const bool over = any(greaterThan(compressed, 1.0.xxx));
+        const bool under = any(lessThan(compressed, 0.0.xxx));
+        return (over || under) ? float3(over ? 1.0 : 0.0, 0.0, under ? 1.0 : 0.0) : debug_backdrop(compressed);
+    } else if (debug_view == DEBUG_VIEW_OUT_OF_GAMUT_INPUT) {
+        // Stimulus with negative components in the display primaries can't be produced by it.
+        if (any(lessThan(BT709_to_display(raw_stimulus), 0.0.xxx))) {
+            return float3(1.0, 0.0, 1.0);
+        }
+        return debug_backdrop(compressed);
+    }
+
+    return compressed;
+}
+
+#endif  // NOTORIOUS6_DEBUG_VIEW_GLSL
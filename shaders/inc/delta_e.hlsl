@@ -0,0 +1,79 @@
+#ifndef NOTORIOUS6_DELTA_E_HLSL
+#define NOTORIOUS6_DELTA_E_HLSL
+
+// CIEDE2000 color difference between two CIELAB colors.
+// http://www2.ece.rochester.edu/~gsharma/ciede2000/ciede2000noteCRNA.pdf
+float delta_e_2000(float3 lab1, float3 lab2) {
+    // 25^7
+    const float POW25_7 = 6103515625.0;
+
+    const float c_bar = 0.5 * (length(lab1.yz) + length(lab2.yz));
+    const float c_bar7 = pow(c_bar, 7.0);
+    const float g = 0.5 * (1.0 - sqrt(c_bar7 / (c_bar7 + POW25_7)));
+
+    const float a1p = (1.0 + g) * lab1.y;
+    const float a2p = (1.0 + g) * lab2.y;
+    const float c1p = length(float2(a1p, lab1.z));
+    const float c2p = length(float2(a2p, lab2.z));
+    const bool achromatic = c1p * c2p == 0.0;
+
+    // Hue angles in degrees, in [0, 360).
+    float h1p = c1p == 0.0 ? 0.0 : degrees(atan2(lab1.z, a1p));
+    float h2p = c2p == 0.0 ? 0.0 : degrees(atan2(lab2.z, a2p));
+    h1p += h1p < 0.0 ? 360.0 : 0.0;
+    h2p += h2p < 0.0 ? 360.0 : 0.0;
+
+    const float dlp = lab2.x - lab1.x;
+    const float dcp = c2p - c1p;
+
+    float dhp = h2p - h1p;
+    if (achromatic) {
+        dhp = 0.0;
+    } else if (dhp > 180.0) {
+        dhp -= 360.0;
+    } else if (dhp < -180.0) {
+        dhp += 360.0;
+    }
+    const float d_hp = 2.0 * sqrt(c1p * c2p) * sin(radians(dhp) * 0.5);
+
+    const float l_bar_p = 0.5 * (lab1.x + lab2.x);
+    const float c_bar_p = 0.5 * (c1p + c2p);
+
+    float h_bar_p = h1p + h2p;
+    if (!achromatic) {
+        if (abs(h1p - h2p) <= 180.0) {
+            h_bar_p *= 0.5;
+        } else if (h_bar_p < 360.0) {
+            h_bar_p = 0.5 * (h_bar_p + 360.0);
+        } else {
+            h_bar_p = 0.5 * (h_bar_p - 360.0);
+        }
+    }
+
+    const float t = 1.0
+        - 0.17 * cos(radians(h_bar_p - 30.0))
+        + 0.24 * cos(radians(2.0 * h_bar_p))
+        + 0.32 * cos(radians(3.0 * h_bar_p + 6.0))
+        - 0.20 * cos(radians(4.0 * h_bar_p - 63.0));
+
+    const float d_theta = 30.0 * exp(-pow((h_bar_p - 275.0) / 25.0, 2.0));
+    const float c_bar_p7 = pow(c_bar_p, 7.0);
+    const float r_c = 2.0 * sqrt(c_bar_p7 / (c_bar_p7 + POW25_7));
+
+    const float l50 = (l_bar_p - 50.0) * (l_bar_p - 50.0);
+    const float s_l = 1.0 + 0.015 * l50 / sqrt(20.0 + l50);
+    const float s_c = 1.0 + 0.045 * c_bar_p;
+    const float s_h = 1.0 + 0.015 * c_bar_p * t;
+    const float r_t = -sin(radians(2.0 * d_theta)) * r_c;
+
+    const float3 terms = float3(dlp / s_l, dcp / s_c, d_hp / s_h);
+    return sqrt(max(0.0, dot(terms, terms) + r_t * terms.y * terms.z));
+}
+
+// ΔE-ITP (ITU-R BT.2124) between two ICtCp colors. 1.0 is roughly one just-noticeable difference.
+float delta_e_itp(float3 ictcp1, float3 ictcp2) {
+    const float3 itp_diff = (ictcp1 - ictcp2) * float3(1.0, 0.5, 1.0);
+    return 720.0 * length(itp_diff);
+}
+
+#endif  // NOTORIOUS6_DELTA_E_HLSL
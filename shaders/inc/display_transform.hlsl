@@ -9,6 +9,7 @@
 #include "ycbcr.hlsl"
 #include "ipt.hlsl"
 #include "bezold_brucke.hlsl"
+#include "output_target.glsl"
 
 // The space to perform chroma attenuation in. More details in the `compress_stimulus` function.
 // Oklab works well, but fails at pure blues.
@@ -215,4 +216,11 @@ float3 display_transform_sRGB(float3 input_stimulus) {
     return compressed_rgb;
 }
 
+// `display_transform_sRGB` targeting the gamut of the display rather than BT.709.
+// The gamut mapping happens against the unit cube of its working RGB, so that is switched
+// to the display primaries. The perceptual models still treat it as BT.709; an approximation.
+float3 display_transform(float3 input_stimulus) {
+    return display_to_BT709(display_transform_sRGB(BT709_to_display(input_stimulus)));
+}
+
 #endif  // NOTORIOUS6_DISPLAY_TRANSFORM_HLSL
@@ -0,0 +1,27 @@
+#ifndef NOTORIOUS6_FRAME_CONSTANTS_GLSL
+#define NOTORIOUS6_FRAME_CONSTANTS_GLSL
+
+// Must match `FrameConstants` in `src/frame_constants.rs`
+layout(std140, binding = 0) uniform FrameConstants {
+    // Of the output, in pixels.
+    float2 frame_resolution;
+    // Over the image, in the same space as `input_uv`; negative when outside of it.
+    // Only updated when the viewer redraws, and negative in batch output.
+    float2 frame_cursor_uv;
+    // Seconds since the start, as of the frame; 0 in batch output.
+    float frame_time;
+    float frame_ev;
+    float frame_display_peak_nits;
+    // -1 for synthetic inputs, such as LUT lattices and analysis grids.
+    int frame_image_index;
+    // Set with `--params`; 0 by default.
+    float4 frame_params[2];
+    // log2 luminance the eye is adapted to, at the EV. Follows the log-average luminance of
+    // earlier frames in the viewer, and in batch sequences rendered with
+    // `--adaptation-frame-rate`; otherwise `image_log2_average + input_ev`.
+    float frame_adapted_log2_luminance;
+};
+
+#define FRAME_PARAM(i) frame_params[(i) / 4][(i) % 4]
+
+#endif  // NOTORIOUS6_FRAME_CONSTANTS_GLSL
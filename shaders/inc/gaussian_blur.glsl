@@ -0,0 +1,18 @@
+// Standard deviation, in texels of the blurred texture. Downscaling the pass widens the blur.
+#define GAUSSIAN_BLUR_SIGMA 2.0
+#define GAUSSIAN_BLUR_RADIUS 6
+
+// One axis of a separable Gaussian blur of `tex`; `direction` is (1, 0) or (0, 1).
+float4 gaussian_blur(sampler2D tex, float2 uv, float2 direction) {
+    const float2 texel_step = direction / float2(textureSize(tex, 0));
+
+    float4 sum = 0.0.xxxx;
+    float weight_sum = 0.0;
+    for (int i = -GAUSSIAN_BLUR_RADIUS; i <= GAUSSIAN_BLUR_RADIUS; ++i) {
+        const float weight = exp(-0.5 * float(i * i) / (GAUSSIAN_BLUR_SIGMA * GAUSSIAN_BLUR_SIGMA));
+        sum += weight * textureLod(tex, uv + float(i) * texel_step, 0);
+        weight_sum += weight;
+    }
+
+    return sum / weight_sum;
+}
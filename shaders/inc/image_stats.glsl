@@ -0,0 +1,54 @@
+#ifndef NOTORIOUS6_IMAGE_STATS_GLSL
+#define NOTORIOUS6_IMAGE_STATS_GLSL
+
+#include "../stats/luminance_histogram_common.glsl"
+
+// Statistics of the whole input image, at EV 0; add `input_ev` to the log2 values for the
+// current exposure. Synthetic inputs, such as LUT lattices, get statistics of their own.
+// All zero until the statistics shader has compiled.
+// Must match `ImageStatsConstants` in `src/image_stats.rs`
+layout(std140, binding = 1) uniform ImageStats {
+    // log2 of the luminance of the darkest pixel which isn't black.
+    float image_log2_min;
+    float image_log2_max;
+    // log2 of the geometric mean luminance; the key of Reinhard et al.
+    float image_log2_average;
+    // Largest R, G, or B.
+    float image_max_component;
+    // Fraction of the pixels up to and including each histogram bin, four bins per element.
+    float4 image_luminance_cdf_bins[LUMINANCE_HISTOGRAM_BIN_COUNT / 4];
+};
+
+float image_luminance_cdf_bin(int bin) {
+    return bin < 0 ? 0.0 : image_luminance_cdf_bins[bin / 4][bin % 4];
+}
+
+// Fraction of the pixels darker than `log2_luminance` (at EV 0), e.g. for histogram equalization.
+float image_luminance_cdf(float log2_luminance) {
+    const float x = LUMINANCE_HISTOGRAM_BIN_COUNT * saturate(
+        (log2_luminance - LUMINANCE_HISTOGRAM_LOG2_MIN)
+        / (LUMINANCE_HISTOGRAM_LOG2_MAX - LUMINANCE_HISTOGRAM_LOG2_MIN));
+    const int bin = min(int(x), LUMINANCE_HISTOGRAM_BIN_COUNT - 1);
+    return mix(image_luminance_cdf_bin(bin - 1), image_luminance_cdf_bin(bin), x - bin);
+}
+
+// log2 luminance (at EV 0) which `fraction` of the pixels are darker than; 0.5 for the median.
+float image_log2_percentile(float fraction) {
+    int lo = 0;
+    int hi = LUMINANCE_HISTOGRAM_BIN_COUNT - 1;
+    while (lo < hi) {
+        const int mid = (lo + hi) / 2;
+        if (image_luminance_cdf_bin(mid) >= fraction) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    const float below = image_luminance_cdf_bin(lo - 1);
+    const float t = saturate((fraction - below) / max(1e-10, image_luminance_cdf_bin(lo) - below));
+    return LUMINANCE_HISTOGRAM_LOG2_MIN
+        + (lo + t) * (LUMINANCE_HISTOGRAM_LOG2_MAX - LUMINANCE_HISTOGRAM_LOG2_MIN) / LUMINANCE_HISTOGRAM_BIN_COUNT;
+}
+
+#endif  // NOTORIOUS6_IMAGE_STATS_GLSL
@@ -0,0 +1,100 @@
+#ifndef NOTORIOUS6_OUTPUT_TARGET_GLSL
+#define NOTORIOUS6_OUTPUT_TARGET_GLSL
+
+#include "ictcp.hlsl"
+
+// Must match `OutputEncoding` in `src/output_target.rs`
+#define OUTPUT_ENCODING_SRGB 0
+#define OUTPUT_ENCODING_PQ 1
+#define OUTPUT_ENCODING_HLG 2
+#define OUTPUT_ENCODING_SCRGB 3
+
+// Must match `DisplayPrimaries` in `src/output_target.rs`
+#define DISPLAY_PRIMARIES_BT709 0
+#define DISPLAY_PRIMARIES_P3 1
+#define DISPLAY_PRIMARIES_BT2020 2
+
+// Techniques output display-linear BT.709, with 1.0 at the peak luminance of the display.
+// Colors outside of BT.709 have negative components; they are converted to the display
+// primaries before encoding. Techniques can read these to adapt to the display,
+// e.g. by leaving more headroom for highlights, or by gamut-mapping with `BT709_to_display`.
+uniform int output_encoding;
+// Luminance of 1.0 in the technique output, in nits.
+uniform float output_peak_nits;
+uniform int display_primaries;
+
+// Display white in scRGB, in nits.
+#define SCRGB_WHITE_NITS 80.0
+
+float3 BT709_to_P3(float3 rgb) {
+    return mul(float3x3(
+        0.8224621, 0.1775380, 0.0000000,
+        0.0331941, 0.9668058, 0.0000000,
+        0.0170827, 0.0723974, 0.9105199
+    ), rgb);
+}
+
+float3 P3_to_BT709(float3 rgb) {
+    return mul(float3x3(
+         1.2249401, -0.2249404,  0.0000000,
+        -0.0420569,  1.0420571,  0.0000000,
+        -0.0196376, -0.0786361,  1.0982735
+    ), rgb);
+}
+
+// BT.709 to the display primaries.
+float3 BT709_to_display(float3 rgb) {
+    if (display_primaries == DISPLAY_PRIMARIES_P3) {
+        return BT709_to_P3(rgb);
+    } else if (display_primaries == DISPLAY_PRIMARIES_BT2020) {
+        return BT709_to_BT2020(rgb);
+    }
+    return rgb;
+}
+
+// The display primaries to BT.709.
+float3 display_to_BT709(float3 rgb) {
+    if (display_primaries == DISPLAY_PRIMARIES_P3) {
+        return P3_to_BT709(rgb);
+    } else if (display_primaries == DISPLAY_PRIMARIES_BT2020) {
+        return BT2020_to_BT709(rgb);
+    }
+    return rgb;
+}
+
+// BT.2100 HLG OETF
+float HLG_OETF(float e) {
+    const float a = 0.17883277;
+    const float b = 0.28466892;
+    const float c = 0.55991073;
+    return e <= 1.0 / 12.0 ? sqrt(3.0 * e) : a * log(12.0 * e - b) + c;
+}
+
+// Display-linear RGB, normalized to the nominal peak, to the HLG signal. Inverts the
+// BT.2100 OOTF, with the system gamma of a display of that peak.
+float3 display_to_HLG(float3 display, float peak_nits) {
+    const float gamma = 1.2 + 0.42 * log(peak_nits / 1000.0) / log(10.0);
+    const float y_d = max(1e-10, dot(display, float3(0.2627, 0.6780, 0.0593)));
+    const float3 scene = display * pow(y_d, (1.0 - gamma) / gamma);
+    return float3(HLG_OETF(scene.r), HLG_OETF(scene.g), HLG_OETF(scene.b));
+}
+
+// Encodes the technique output for the framebuffer. sRGB is left linear,
+// as the framebuffer does the encoding.
+float3 encode_output_target(float3 rgb) {
+    // scRGB is always BT.709, with negative components outside of it.
+    if (output_encoding == OUTPUT_ENCODING_SCRGB) {
+        return rgb * (output_peak_nits / SCRGB_WHITE_NITS);
+    }
+
+    rgb = max(0.0.xxx, BT709_to_display(rgb));
+
+    if (output_encoding == OUTPUT_ENCODING_PQ) {
+        return linear_to_PQ(rgb * output_peak_nits);
+    } else if (output_encoding == OUTPUT_ENCODING_HLG) {
+        return display_to_HLG(saturate(rgb), output_peak_nits);
+    }
+    return rgb;
+}
+
+#endif  // NOTORIOUS6_OUTPUT_TARGET_GLSL
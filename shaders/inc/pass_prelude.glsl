@@ -0,0 +1,18 @@
+#version 430
+#include "hlsl_to_glsl.glsl"
+#include "math.hlsl"
+#include "frame_constants.glsl"
+#include "image_stats.glsl"
+
+// Intermediate pass of a technique, declared with `// pass:`; see `src/pass_graph.rs`.
+// Earlier passes are samplers named after them, the same way up as `input_texture`.
+
+// Scene-linear, with BT.709 primaries, before the EV is applied.
+uniform sampler2D input_texture;
+uniform float input_ev;
+// Output of the pass declared just before this one, or `input_texture` for the first.
+uniform sampler2D previous_pass;
+in float2 input_uv;
+out float4 output_rgba;
+
+#define SHADER_MAIN_FN output_rgba = run_pass(input_uv);
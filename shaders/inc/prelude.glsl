@@ -1,6 +1,10 @@
 #version 430
 #include "hlsl_to_glsl.glsl"
 #include "math.hlsl"
+#include "debug_view.glsl"
+#include "output_target.glsl"
+#include "frame_constants.glsl"
+#include "image_stats.glsl"
 
 uniform sampler2D input_texture;
 uniform float input_ev;
@@ -10,6 +14,8 @@ out float4 output_rgba;
 #define DECLARE_BEZOLD_BRUCKE_LUT uniform sampler1D bezold_brucke_lut
 #define SAMPLE_BEZOLD_BRUCKE_LUT(coord) textureLod(bezold_brucke_lut, (coord), 0).xy
 
+// The stimulus is scene-linear, with BT.709 primaries. This is also the reference
+// color space of configs written by `export-ocio`.
 struct ShaderInput {
     float3 stimulus;
     float2 uv;
@@ -22,4 +28,9 @@ ShaderInput prepare_shader_input() {
     return shader_input;
 }
 
-#define SHADER_MAIN_FN output_rgba = float4(compress_stimulus(prepare_shader_input()), 1.0);
+float3 apply_debug_view(ShaderInput shader_input, float3 compressed) {
+    const float3 raw_stimulus = exp2(input_ev) * textureLod(input_texture, input_uv, 0).rgb;
+    return apply_debug_view(raw_stimulus, shader_input.stimulus, compressed);
+}
+
+#define SHADER_MAIN_FN ShaderInput shader_input = prepare_shader_input(); output_rgba = float4(encode_output_target(apply_debug_view(shader_input, compress_stimulus(shader_input))), 1.0);
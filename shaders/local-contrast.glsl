@@ -0,0 +1,28 @@
+// TL;DR: Compress the local average brightness, keep the detail around it.
+// A local operator built from passes: the log-luminance is blurred at 1/8 resolution,
+// and only that base layer is compressed, like in Durand and Dorsey's, but without the bilateral filter.
+
+// pass: shader=passes/log_luminance.glsl scale=0.125 format=r16f
+// pass: shader=passes/blur_h.glsl scale=0.125 format=r16f
+// pass: name=local_log_luminance shader=passes/blur_v.glsl scale=0.125 format=r16f
+
+#include "inc/prelude.glsl"
+#include "inc/srgb.hlsl"
+
+uniform sampler2D local_log_luminance;
+
+// Of the base layer; 1 is global-only compression.
+#define BASE_CONTRAST 0.5
+
+float3 compress_stimulus(ShaderInput shader_input) {
+    const float luminance = max(1e-10, sRGB_to_luminance(shader_input.stimulus));
+    const float base = textureLod(local_log_luminance, shader_input.uv, 0).x;
+    const float detail = log2(luminance) - base;
+
+    // Around middle gray, so that it stays put.
+    const float middle_gray = log2(0.18);
+    const float compressed_luminance = exp2(middle_gray + (base - middle_gray) * BASE_CONTRAST + detail);
+
+    const float3 stimulus = shader_input.stimulus * (compressed_luminance / luminance);
+    return 1.0 - exp(-stimulus);
+}
@@ -1,4 +1,5 @@
 #version 430
+// lut: name=bezold_brucke_lut size=64 format=rg32f wrap=repeat
 #include "../inc/hlsl_to_glsl.glsl"
 #include "../inc/math.hlsl"
 
@@ -0,0 +1,7 @@
+#include "../inc/pass_prelude.glsl"
+#include "../inc/gaussian_blur.glsl"
+
+// The vertical half of a separable Gaussian blur of `previous_pass`.
+float4 run_pass(float2 uv) {
+    return gaussian_blur(previous_pass, uv, float2(0.0, 1.0));
+}
@@ -0,0 +1,8 @@
+#include "../inc/pass_prelude.glsl"
+#include "../inc/srgb.hlsl"
+
+// log2 of the luminance, with the EV applied.
+float4 run_pass(float2 uv) {
+    const float3 stimulus = exp2(input_ev) * max(0.0.xxx, textureLod(input_texture, uv, 0).rgb);
+    return float4(log2(max(1e-10, sRGB_to_luminance(stimulus))).xxx, 1.0);
+}
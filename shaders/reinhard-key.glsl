@@ -0,0 +1,18 @@
+// TL;DR: Reinhard et al.'s photographic operator, keyed to the log-average luminance of the image.
+// The EV is an exposure bias on top of the key, and white is the brightest pixel.
+// Keyed to what the eye is adapted to, which lags behind the image in sequences.
+
+#include "inc/prelude.glsl"
+#include "inc/srgb.hlsl"
+
+#define KEY_VALUE 0.18
+
+float3 compress_stimulus(ShaderInput shader_input) {
+    // `image_log2_average + input_ev` once adapted.
+    const float scale = KEY_VALUE * exp2(input_ev - frame_adapted_log2_luminance);
+    const float white = max(1.0, exp2(image_log2_max + input_ev) * scale);
+
+    const float luminance = max(1e-10, scale * sRGB_to_luminance(shader_input.stimulus));
+    const float compressed = luminance * (1.0 + luminance / (white * white)) / (1.0 + luminance);
+    return shader_input.stimulus * scale * (compressed / luminance);
+}
@@ -0,0 +1,38 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "../inc/srgb.hlsl"
+#include "../inc/xyz.hlsl"
+#include "chromaticity_common.glsl"
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+uniform sampler2D input_texture;
+uniform sampler2D output_texture;
+
+void accumulate(float3 linear_rgb, int offset) {
+    const float3 XYZ = sRGB_to_XYZ(linear_rgb);
+
+    // Chromaticity is undefined for black, and meaningless for "negative light".
+    if (XYZ.y <= 0.0 || XYZ.x + XYZ.y + XYZ.z <= 0.0) {
+        return;
+    }
+
+    const float2 xy = CIE_XYZ_to_xyY(XYZ).xy;
+    const ivec2 bin = ivec2(xy / CHROMATICITY_XY_EXTENT * CHROMATICITY_RESOLUTION);
+
+    if (all(greaterThanEqual(bin, ivec2(0))) && all(lessThan(bin, ivec2(CHROMATICITY_RESOLUTION)))) {
+        atomicAdd(chromaticity_bins[offset + bin.y * CHROMATICITY_RESOLUTION + bin.x], 1u);
+    }
+}
+
+void main() {
+    const ivec2 px = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(px, textureSize(input_texture, 0)))) {
+        return;
+    }
+
+    // Note: not clamped to the BT.709 gamut like in `prepare_shader_input`,
+    // so that out-of-gamut input is visible. Exposure doesn't affect chromaticity.
+    accumulate(texelFetch(input_texture, px, 0).rgb, CHROMATICITY_INPUT_OFFSET);
+    accumulate(texelFetch(output_texture, px, 0).rgb, CHROMATICITY_OUTPUT_OFFSET);
+}
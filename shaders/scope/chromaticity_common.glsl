@@ -0,0 +1,18 @@
+#ifndef NOTORIOUS6_CHROMATICITY_COMMON_GLSL
+#define NOTORIOUS6_CHROMATICITY_COMMON_GLSL
+
+// Must match `CHROMATICITY_RESOLUTION` in `src/chromaticity.rs`
+#define CHROMATICITY_RESOLUTION 256
+
+// The square area of the xy plane covered by the diagram; fits the whole spectral locus.
+#define CHROMATICITY_XY_EXTENT 0.9
+
+// Input stimulus, followed by the display output; `CHROMATICITY_RESOLUTION`^2 bins each.
+#define CHROMATICITY_INPUT_OFFSET 0
+#define CHROMATICITY_OUTPUT_OFFSET (CHROMATICITY_RESOLUTION * CHROMATICITY_RESOLUTION)
+
+layout(std430, binding = 0) buffer chromaticity_buffer {
+    uint chromaticity_bins[];
+};
+
+#endif  // NOTORIOUS6_CHROMATICITY_COMMON_GLSL
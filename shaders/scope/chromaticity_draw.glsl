@@ -0,0 +1,82 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "../inc/srgb.hlsl"
+#include "../inc/standard_observer.hlsl"
+#include "chromaticity_common.glsl"
+
+// Total number of image pixels.
+uniform float pixel_count;
+
+in float2 input_uv;
+out float4 output_rgba;
+
+float distance_to_segment(float2 p, float2 a, float2 b) {
+    const float2 pa = p - a;
+    const float2 ba = b - a;
+    const float h = saturate(dot(pa, ba) / dot(ba, ba));
+    return length(pa - ba * h);
+}
+
+float distance_to_triangle(float2 p, float2 a, float2 b, float2 c) {
+    return min(distance_to_segment(p, a, b), min(distance_to_segment(p, b, c), distance_to_segment(p, c, a)));
+}
+
+// Relative to all pixels landing in a single bin, with a steep curve so that sparse colors are visible.
+float bin_intensity(int offset, ivec2 bin) {
+    const uint count = chromaticity_bins[offset + bin.y * CHROMATICITY_RESOLUTION + bin.x];
+    return pow(float(count) / pixel_count, 0.2);
+}
+
+float4 draw_chromaticity(float2 uv) {
+    const float2 xy = float2(uv.x, 1.0 - uv.y) * CHROMATICITY_XY_EXTENT;
+    const float line_width = fwidth(xy.x) * 1.5;
+
+    float4 result = float4(0.0, 0.0, 0.0, 0.6);
+
+    // Spectral locus, closed by the line of purples. Also find whether `xy` is inside it.
+    float locus_distance = 1e10;
+    bool inside_locus = false;
+    for (int i = 0; i < standard_observer_1931_length; ++i) {
+        const float2 a = CIE_XYZ_to_xyY(standard_observer_1931[i]).xy;
+        const float2 b = CIE_XYZ_to_xyY(standard_observer_1931[(i + 1) % standard_observer_1931_length]).xy;
+        locus_distance = min(locus_distance, distance_to_segment(xy, a, b));
+
+        if ((a.y > xy.y) != (b.y > xy.y) && xy.x < (b.x - a.x) * (xy.y - a.y) / (b.y - a.y) + a.x) {
+            inside_locus = !inside_locus;
+        }
+    }
+
+    // Faint colors of the visible chromaticities as the backdrop.
+    if (inside_locus) {
+        const float3 rgb = max(0.0.xxx, XYZ_to_sRGB(CIE_xyY_to_XYZ(float3(xy, 1.0))));
+        result.rgb += 0.15 * rgb / max(rgb.r, max(rgb.g, rgb.b));
+    }
+
+    const ivec2 bin = clamp(ivec2(xy / CHROMATICITY_XY_EXTENT * CHROMATICITY_RESOLUTION), 0, CHROMATICITY_RESOLUTION - 1);
+
+    // Input in cyan, output in orange; where both overlap, the sum is whitish.
+    result.rgb += float3(0.0, 0.6, 1.0) * bin_intensity(CHROMATICITY_INPUT_OFFSET, bin);
+    result.rgb += float3(1.0, 0.5, 0.0) * bin_intensity(CHROMATICITY_OUTPUT_OFFSET, bin);
+
+    if (locus_distance < line_width) {
+        result = float4(0.8.xxx, 1.0);
+    }
+
+    if (distance_to_triangle(xy, float2(0.708, 0.292), float2(0.170, 0.797), float2(0.131, 0.046)) < line_width) {
+        // BT.2020
+        result = float4(0.4, 0.4, 0.9, 1.0);
+    }
+
+    if (distance_to_triangle(xy, float2(0.64, 0.33), float2(0.30, 0.60), float2(0.15, 0.06)) < line_width) {
+        // sRGB / BT.709
+        result = float4(0.9, 0.9, 0.4, 1.0);
+    }
+
+    if (length(xy - white_D65_xy) < line_width * 2.0) {
+        result = float4(1.0.xxx, 1.0);
+    }
+
+    return result;
+}
+
+#define SHADER_MAIN_FN output_rgba = draw_chromaticity(input_uv);
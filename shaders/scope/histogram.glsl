@@ -0,0 +1,39 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "../inc/srgb.hlsl"
+#include "histogram_common.glsl"
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+uniform sampler2D input_texture;
+uniform sampler2D output_texture;
+uniform float input_ev;
+
+uint input_bin(float value) {
+    const float x = histogram_input_log2_to_x(log2(max(1e-20, value)));
+    return uint(clamp(x * HISTOGRAM_BIN_COUNT, 0.0, HISTOGRAM_BIN_COUNT - 1.0));
+}
+
+uint output_bin(float value) {
+    return uint(clamp(value * HISTOGRAM_BIN_COUNT, 0.0, HISTOGRAM_BIN_COUNT - 1.0));
+}
+
+void main() {
+    const ivec2 px = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(px, textureSize(input_texture, 0)))) {
+        return;
+    }
+
+    // Same as `prepare_shader_input` in the prelude.
+    const float3 stimulus = exp2(input_ev) * max(0.0.xxx, texelFetch(input_texture, px, 0).rgb);
+    const float4 input_values = float4(stimulus, sRGB_to_luminance(stimulus));
+
+    // The output is read through an sRGB texture, so this is linear. Encode it back to display code values.
+    const float3 output_linear = texelFetch(output_texture, px, 0).rgb;
+    const float4 output_values = float4(sRGB_OETF(output_linear), sRGB_OETF(sRGB_to_luminance(output_linear)));
+
+    for (int c = 0; c < 4; ++c) {
+        atomicAdd(histogram_bins[HISTOGRAM_INPUT_OFFSET + c * HISTOGRAM_BIN_COUNT + input_bin(input_values[c])], 1u);
+        atomicAdd(histogram_bins[HISTOGRAM_OUTPUT_OFFSET + c * HISTOGRAM_BIN_COUNT + output_bin(output_values[c])], 1u);
+    }
+}
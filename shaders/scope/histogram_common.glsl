@@ -0,0 +1,23 @@
+#ifndef NOTORIOUS6_HISTOGRAM_COMMON_GLSL
+#define NOTORIOUS6_HISTOGRAM_COMMON_GLSL
+
+// Must match `HISTOGRAM_BIN_COUNT` in `src/histogram.rs`
+#define HISTOGRAM_BIN_COUNT 256
+
+// The input stimulus is binned by the log2 of each channel, and of its luminance.
+#define HISTOGRAM_INPUT_LOG2_MIN -16.0
+#define HISTOGRAM_INPUT_LOG2_MAX 16.0
+
+// Each histogram has R, G, B, and luminance channels, `HISTOGRAM_BIN_COUNT` bins each.
+#define HISTOGRAM_INPUT_OFFSET 0
+#define HISTOGRAM_OUTPUT_OFFSET (4 * HISTOGRAM_BIN_COUNT)
+
+layout(std430, binding = 0) buffer histogram_buffer {
+    uint histogram_bins[];
+};
+
+float histogram_input_log2_to_x(float log2_value) {
+    return (log2_value - HISTOGRAM_INPUT_LOG2_MIN) / (HISTOGRAM_INPUT_LOG2_MAX - HISTOGRAM_INPUT_LOG2_MIN);
+}
+
+#endif  // NOTORIOUS6_HISTOGRAM_COMMON_GLSL
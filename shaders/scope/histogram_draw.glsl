@@ -0,0 +1,62 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "histogram_common.glsl"
+
+in float2 input_uv;
+out float4 output_rgba;
+
+bool is_on_line(float x, float line_x) {
+    return abs(x - line_x) < fwidth(x);
+}
+
+float4 draw_histogram(float2 uv) {
+    // Input stimulus in the top half, display output in the bottom half.
+    const bool is_input = uv.y < 0.5;
+    const float height = is_input ? 1.0 - uv.y * 2.0 : 2.0 - uv.y * 2.0;
+    const int offset = is_input ? HISTOGRAM_INPUT_OFFSET : HISTOGRAM_OUTPUT_OFFSET;
+    const int bin = clamp(int(uv.x * HISTOGRAM_BIN_COUNT), 0, HISTOGRAM_BIN_COUNT - 1);
+
+    // Normalize by the tallest bin, skipping the first and last ones,
+    // as those collect everything outside of the range.
+    uint max_count = 1;
+    for (int c = 0; c < 4; ++c) {
+        for (int i = 1; i + 1 < HISTOGRAM_BIN_COUNT; ++i) {
+            max_count = max(max_count, histogram_bins[offset + c * HISTOGRAM_BIN_COUNT + i]);
+        }
+    }
+
+    float4 result = float4(0.0, 0.0, 0.0, 0.6);
+
+    for (int c = 0; c < 3; ++c) {
+        if (height * max_count < histogram_bins[offset + c * HISTOGRAM_BIN_COUNT + bin]) {
+            result[c] += 0.7;
+        }
+    }
+
+    if (height * max_count < histogram_bins[offset + 3 * HISTOGRAM_BIN_COUNT + bin]) {
+        result.rgb += 0.25;
+    }
+
+    if (is_input) {
+        // Every 4 stops, plus "display white" at 1.0, and mid-gray.
+        for (float ev = HISTOGRAM_INPUT_LOG2_MIN; ev <= HISTOGRAM_INPUT_LOG2_MAX; ev += 4.0) {
+            if (is_on_line(uv.x, histogram_input_log2_to_x(ev))) {
+                result.rgb += 0.15;
+            }
+        }
+
+        if (is_on_line(uv.x, histogram_input_log2_to_x(0.0))) {
+            result = float4(1.0, 1.0, 1.0, 1.0);
+        }
+
+        if (is_on_line(uv.x, histogram_input_log2_to_x(log2(0.18)))) {
+            result = float4(0.5, 0.5, 0.5, 1.0);
+        }
+    } else if (is_on_line(uv.y, 0.5)) {
+        result = float4(0.5, 0.5, 0.5, 1.0);
+    }
+
+    return result;
+}
+
+#define SHADER_MAIN_FN output_rgba = draw_histogram(input_uv);
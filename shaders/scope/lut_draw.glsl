@@ -0,0 +1,77 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+
+uniform sampler1D lut_1d;
+uniform sampler2D lut_2d;
+uniform sampler3D lut_3d;
+uniform int lut_dims;
+uniform int lut_channel_count;
+// Min and max of the finite values in the LUT.
+uniform float2 lut_value_range;
+
+in float2 input_uv;
+out float4 output_rgba;
+
+float3 channel_color(int c) {
+    if (c == 0) return float3(1.0, 0.3, 0.3);
+    if (c == 1) return float3(0.3, 1.0, 0.3);
+    if (c == 2) return float3(0.4, 0.5, 1.0);
+    return float3(0.9, 0.9, 0.9);
+}
+
+bool is_non_finite(float4 values) {
+    for (int c = 0; c < lut_channel_count; ++c) {
+        if (isnan(values[c]) || isinf(values[c])) {
+            return true;
+        }
+    }
+    return false;
+}
+
+float4 draw_lut(float2 uv) {
+    const float range = max(1e-10, lut_value_range.y - lut_value_range.x);
+
+    float4 values;
+    if (lut_dims == 1) {
+        values = textureLod(lut_1d, uv.x, 0);
+    } else if (lut_dims == 2) {
+        values = textureLod(lut_2d, float2(uv.x, 1.0 - uv.y), 0);
+    } else {
+        values = textureLod(lut_3d, float3(uv.x, 1.0 - uv.y, 0.5), 0);
+    }
+
+    // NaN and infinity in magenta.
+    if (is_non_finite(values)) {
+        return float4(1.0, 0.0, 1.0, 1.0);
+    }
+
+    const float4 normalized = (values - lut_value_range.x) / range;
+
+    if (lut_dims == 1) {
+        // One curve per channel.
+        const float height = 1.0 - uv.y;
+        float4 result = float4(0.0, 0.0, 0.0, 0.6);
+
+        // Zero, if it's in range.
+        if (abs(height - (0.0 - lut_value_range.x) / range) < fwidth(height)) {
+            result.rgb += 0.3;
+        }
+
+        for (int c = 0; c < lut_channel_count; ++c) {
+            const float thickness = max(fwidth(height), abs(dFdx(normalized[c])));
+            if (abs(height - normalized[c]) < thickness) {
+                result = float4(channel_color(c), 1.0);
+            }
+        }
+
+        return result;
+    } else {
+        // Channels as RGB; a single channel as gray.
+        const float3 rgb = lut_channel_count == 1
+            ? normalized.rrr
+            : float3(normalized.rg, lut_channel_count > 2 ? normalized.b : 0.0);
+        return float4(saturate(rgb), 1.0);
+    }
+}
+
+#define SHADER_MAIN_FN output_rgba = draw_lut(input_uv);
@@ -0,0 +1,94 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "../inc/srgb.hlsl"
+
+// Must match the constants in `src/tone_curve.rs`
+#define TONE_CURVE_SAMPLE_COUNT 256
+#define TONE_CURVE_LOG2_MIN -10.0
+#define TONE_CURVE_LOG2_MAX 6.0
+
+// Output luminance. Grey, red, green, and blue of the current technique,
+// followed by the grey curves of `pinned_count` pinned techniques.
+layout(std430, binding = 0) buffer tone_curves_buf {
+    float tone_curves[];
+};
+
+uniform int pinned_count;
+
+in float2 input_uv;
+out float4 output_rgba;
+
+// Output luminance is plotted sRGB-encoded, up to this value.
+#define TONE_CURVE_PLOT_MAX 1.1
+
+float tone_curve_log2_to_x(float log2_stimulus) {
+    return (log2_stimulus - TONE_CURVE_LOG2_MIN) / (TONE_CURVE_LOG2_MAX - TONE_CURVE_LOG2_MIN);
+}
+
+float sample_curve(int curve, float x) {
+    const float pos = saturate(x) * (TONE_CURVE_SAMPLE_COUNT - 1);
+    const int i = min(int(pos), TONE_CURVE_SAMPLE_COUNT - 2);
+    const float a = tone_curves[curve * TONE_CURVE_SAMPLE_COUNT + i];
+    const float b = tone_curves[curve * TONE_CURVE_SAMPLE_COUNT + i + 1];
+    return sRGB_OETF(max(0.0, mix(a, b, pos - i))) / TONE_CURVE_PLOT_MAX;
+}
+
+bool is_on_curve(int curve, float x, float height, float2 pixel_size) {
+    const float y = sample_curve(curve, x);
+    const float slope = abs(sample_curve(curve, x + pixel_size.x) - y);
+    return abs(height - y) < max(pixel_size.y, slope);
+}
+
+float4 draw_tone_curves(float2 uv) {
+    const float height = 1.0 - uv.y;
+    const float2 pixel_size = fwidth(float2(uv.x, height));
+
+    float4 result = float4(0.0, 0.0, 0.0, 0.6);
+
+    // Every 2 stops, plus mid-gray.
+    for (float ev = TONE_CURVE_LOG2_MIN; ev <= TONE_CURVE_LOG2_MAX; ev += 2.0) {
+        if (abs(uv.x - tone_curve_log2_to_x(ev)) < pixel_size.x) {
+            result.rgb += 0.15;
+        }
+    }
+
+    if (abs(uv.x - tone_curve_log2_to_x(log2(0.18))) < pixel_size.x) {
+        result.rgb += 0.3;
+    }
+
+    // Display white.
+    if (abs(height - 1.0 / TONE_CURVE_PLOT_MAX) < pixel_size.y) {
+        result.rgb += 0.3;
+    }
+
+    const float3 pinned_colors[4] = float3[](
+        float3(1.0, 0.8, 0.2),
+        float3(0.2, 0.9, 0.9),
+        float3(1.0, 0.4, 1.0),
+        float3(1.0, 0.6, 0.2)
+    );
+
+    for (int i = 0; i < pinned_count; ++i) {
+        if (is_on_curve(4 + i, uv.x, height, pixel_size)) {
+            result = float4(pinned_colors[i] * 0.8, 1.0);
+        }
+    }
+
+    const float3 current_colors[4] = float3[](
+        float3(1.0, 1.0, 1.0),
+        float3(1.0, 0.3, 0.3),
+        float3(0.3, 1.0, 0.3),
+        float3(0.4, 0.5, 1.0)
+    );
+
+    // Saturated primaries first, so that grey ends up on top.
+    for (int i = 3; i >= 0; --i) {
+        if (is_on_curve(i, uv.x, height, pixel_size)) {
+            result = float4(current_colors[i], 1.0);
+        }
+    }
+
+    return result;
+}
+
+#define SHADER_MAIN_FN output_rgba = draw_tone_curves(input_uv);
@@ -0,0 +1,19 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "vectorscope_common.glsl"
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+uniform sampler2D output_texture;
+
+void main() {
+    const ivec2 px = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(px, textureSize(output_texture, 0)))) {
+        return;
+    }
+
+    const float2 coord = vectorscope_coord(texelFetch(output_texture, px, 0).rgb);
+    const ivec2 bin = clamp(ivec2(coord * VECTORSCOPE_RESOLUTION), 0, VECTORSCOPE_RESOLUTION - 1);
+
+    atomicAdd(vectorscope_bins[bin.y * VECTORSCOPE_RESOLUTION + bin.x], 1u);
+}
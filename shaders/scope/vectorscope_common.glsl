@@ -0,0 +1,32 @@
+#ifndef NOTORIOUS6_VECTORSCOPE_COMMON_GLSL
+#define NOTORIOUS6_VECTORSCOPE_COMMON_GLSL
+
+#include "../inc/srgb.hlsl"
+#include "../inc/ycbcr.hlsl"
+#include "../inc/oklab.hlsl"
+
+// Must match `VECTORSCOPE_RESOLUTION` in `src/vectorscope.rs`
+#define VECTORSCOPE_RESOLUTION 256
+
+// Must match `VectorscopeMode` in `src/vectorscope.rs`
+#define VECTORSCOPE_MODE_YCBCR 0
+#define VECTORSCOPE_MODE_OKLAB 1
+
+layout(std430, binding = 0) buffer vectorscope_buffer {
+    uint vectorscope_bins[];
+};
+
+uniform int vectorscope_mode;
+
+// Position of a linear sRGB color on the scope, with 0.5 at the center and +Y up.
+float2 vectorscope_coord(float3 linear_rgb) {
+    if (vectorscope_mode == VECTORSCOPE_MODE_YCBCR) {
+        // Cb and Cr are within -0.5..0.5 for code values in the 0..1 range.
+        return sRGB_to_YCbCr(sRGB_OETF(saturate(linear_rgb))).yz + 0.5;
+    } else {
+        // Chroma of the sRGB primaries is at most about 0.32 in Oklab.
+        return sRGB_to_Oklab(max(0.0.xxx, linear_rgb)).yz * 1.4 + 0.5;
+    }
+}
+
+#endif  // NOTORIOUS6_VECTORSCOPE_COMMON_GLSL
@@ -0,0 +1,51 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "vectorscope_common.glsl"
+
+// Total number of image pixels.
+uniform float pixel_count;
+
+in float2 input_uv;
+out float4 output_rgba;
+
+float4 draw_vectorscope(float2 uv) {
+    const float2 coord = float2(uv.x, 1.0 - uv.y);
+    const float2 pixel_size = fwidth(coord);
+
+    if (length(coord - 0.5) > 0.5) {
+        return 0.0.xxxx;
+    }
+
+    float4 result = float4(0.0, 0.0, 0.0, 0.6);
+
+    const ivec2 bin = clamp(ivec2(coord * VECTORSCOPE_RESOLUTION), 0, VECTORSCOPE_RESOLUTION - 1);
+    const uint count = vectorscope_bins[bin.y * VECTORSCOPE_RESOLUTION + bin.x];
+
+    // Relative to all pixels landing in a single bin, with a steep curve so that sparse colors are visible.
+    result.rgb += pow(float(count) / pixel_count, 0.2);
+
+    // Crosshair and the outer circle
+    if (any(lessThan(abs(coord - 0.5), pixel_size)) || abs(length(coord - 0.5) - 0.5) < pixel_size.x * 1.5) {
+        result.rgb += 0.2;
+    }
+
+    // Targets for 75% primaries and secondaries, drawn in their own colors.
+    const float3 targets[6] = float3[6](
+        float3(1, 0, 0), float3(1, 1, 0), float3(0, 1, 0),
+        float3(0, 1, 1), float3(0, 0, 1), float3(1, 0, 1)
+    );
+
+    for (int i = 0; i < 6; ++i) {
+        const float3 target_rgb = sRGB_EOTF(0.75 * targets[i]);
+        const float2 target_coord = vectorscope_coord(target_rgb);
+        const float2 offset = abs(coord - target_coord);
+
+        if (max(offset.x, offset.y) < 4.0 * pixel_size.x && max(offset.x, offset.y) > 2.5 * pixel_size.x) {
+            result = float4(targets[i], 1.0);
+        }
+    }
+
+    return result;
+}
+
+#define SHADER_MAIN_FN output_rgba = draw_vectorscope(input_uv);
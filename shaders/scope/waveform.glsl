@@ -0,0 +1,31 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "../inc/srgb.hlsl"
+#include "waveform_common.glsl"
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+uniform sampler2D output_texture;
+
+int waveform_level(float value) {
+    return int(clamp(value * WAVEFORM_LEVEL_COUNT, 0.0, WAVEFORM_LEVEL_COUNT - 1.0));
+}
+
+void main() {
+    const ivec2 px = ivec2(gl_GlobalInvocationID.xy);
+    const ivec2 size = textureSize(output_texture, 0);
+    if (any(greaterThanEqual(px, size))) {
+        return;
+    }
+
+    // The output is read through an sRGB texture; encode it back to display code values,
+    // and calculate luma from those, like a video waveform monitor would.
+    const float3 rgb = sRGB_OETF(texelFetch(output_texture, px, 0).rgb);
+    const float4 values = float4(sRGB_to_luminance(rgb), rgb);
+
+    const int column = px.x * WAVEFORM_COLUMN_COUNT / size.x;
+
+    for (int c = 0; c < 4; ++c) {
+        atomicAdd(waveform_bins[waveform_bin_index(c, column, waveform_level(values[c]))], 1u);
+    }
+}
@@ -0,0 +1,17 @@
+#ifndef NOTORIOUS6_WAVEFORM_COMMON_GLSL
+#define NOTORIOUS6_WAVEFORM_COMMON_GLSL
+
+// Must match `WAVEFORM_COLUMN_COUNT` and `WAVEFORM_LEVEL_COUNT` in `src/waveform.rs`
+#define WAVEFORM_COLUMN_COUNT 256
+#define WAVEFORM_LEVEL_COUNT 256
+
+// Luma, R, G, and B; each with `WAVEFORM_COLUMN_COUNT` columns of `WAVEFORM_LEVEL_COUNT` levels.
+layout(std430, binding = 0) buffer waveform_buffer {
+    uint waveform_bins[];
+};
+
+uint waveform_bin_index(int channel, int column, int level) {
+    return (channel * WAVEFORM_COLUMN_COUNT + column) * WAVEFORM_LEVEL_COUNT + level;
+}
+
+#endif  // NOTORIOUS6_WAVEFORM_COMMON_GLSL
@@ -0,0 +1,54 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "waveform_common.glsl"
+
+// Must match `WaveformMode` in `src/waveform.rs`
+#define WAVEFORM_MODE_LUMA 0
+#define WAVEFORM_MODE_RGB_PARADE 1
+
+uniform int waveform_mode;
+
+// Average number of image pixels falling into each column.
+uniform float pixels_per_column;
+
+in float2 input_uv;
+out float4 output_rgba;
+
+// Maps a hit count to trace intensity. Roughly 0.2 for pixels spread uniformly over the levels.
+float trace_intensity(uint count) {
+    return 1.0 - exp(-float(count) * WAVEFORM_LEVEL_COUNT / (4.0 * pixels_per_column));
+}
+
+float4 draw_waveform(float2 uv) {
+    const float level_value = 1.0 - uv.y;
+    const int level = clamp(int(level_value * WAVEFORM_LEVEL_COUNT), 0, WAVEFORM_LEVEL_COUNT - 1);
+
+    float4 result = float4(0.0, 0.0, 0.0, 0.6);
+
+    if (waveform_mode == WAVEFORM_MODE_LUMA) {
+        const int column = clamp(int(uv.x * WAVEFORM_COLUMN_COUNT), 0, WAVEFORM_COLUMN_COUNT - 1);
+        result.rgb += trace_intensity(waveform_bins[waveform_bin_index(0, column, level)]);
+    } else {
+        // R, G, and B side by side.
+        const int panel = clamp(int(uv.x * 3.0), 0, 2);
+        const float panel_x = uv.x * 3.0 - panel;
+        const int column = clamp(int(panel_x * WAVEFORM_COLUMN_COUNT), 0, WAVEFORM_COLUMN_COUNT - 1);
+
+        result[panel] += trace_intensity(waveform_bins[waveform_bin_index(1 + panel, column, level)]);
+
+        if (panel > 0 && abs(uv.x * 3.0 - panel) < fwidth(uv.x * 3.0)) {
+            result.rgb = 0.5.xxx;
+            result.a = 1.0;
+        }
+    }
+
+    // Graticule every 10%
+    const float graticule = level_value * 10.0;
+    if (abs(graticule - round(graticule)) < fwidth(graticule)) {
+        result.rgb += 0.15;
+    }
+
+    return result;
+}
+
+#define SHADER_MAIN_FN output_rgba = draw_waveform(input_uv);
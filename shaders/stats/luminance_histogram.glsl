@@ -0,0 +1,47 @@
+#version 430
+#include "../inc/hlsl_to_glsl.glsl"
+#include "../inc/srgb.hlsl"
+#include "luminance_histogram_common.glsl"
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+uniform sampler2D input_texture;
+
+layout(std430, binding = 0) buffer luminance_histogram_buffer {
+    uint luminance_histogram_bins[];
+};
+
+// Bits of non-negative floats, which order the same as the floats.
+// Must match `extremes_buffer` in `ImageStats::compute` in `src/image_stats.rs`
+layout(std430, binding = 1) buffer extremes_buffer {
+    // Of pixels which aren't black.
+    uint min_luminance_bits;
+    uint max_luminance_bits;
+    uint max_component_bits;
+};
+
+void main() {
+    const ivec2 px = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(px, textureSize(input_texture, 0)))) {
+        return;
+    }
+
+    const float3 stimulus = max(0.0.xxx, texelFetch(input_texture, px, 0).rgb);
+    if (any(isnan(stimulus)) || any(isinf(stimulus))) {
+        return;
+    }
+
+    // At EV 0; black pixels end up in the first bin.
+    const float luminance = sRGB_to_luminance(stimulus);
+    const float t = (log2(max(1e-20, luminance)) - LUMINANCE_HISTOGRAM_LOG2_MIN)
+        / (LUMINANCE_HISTOGRAM_LOG2_MAX - LUMINANCE_HISTOGRAM_LOG2_MIN);
+    const uint bin = uint(clamp(t * LUMINANCE_HISTOGRAM_BIN_COUNT, 0.0, LUMINANCE_HISTOGRAM_BIN_COUNT - 1.0));
+
+    atomicAdd(luminance_histogram_bins[bin], 1u);
+
+    if (luminance > 0.0) {
+        atomicMin(min_luminance_bits, floatBitsToUint(luminance));
+    }
+    atomicMax(max_luminance_bits, floatBitsToUint(luminance));
+    atomicMax(max_component_bits, floatBitsToUint(max(stimulus.r, max(stimulus.g, stimulus.b))));
+}
@@ -0,0 +1,9 @@
+#ifndef NOTORIOUS6_LUMINANCE_HISTOGRAM_COMMON_GLSL
+#define NOTORIOUS6_LUMINANCE_HISTOGRAM_COMMON_GLSL
+
+// Must match the `LUMINANCE_HISTOGRAM_*` constants in `src/image_stats.rs`
+#define LUMINANCE_HISTOGRAM_BIN_COUNT 256
+#define LUMINANCE_HISTOGRAM_LOG2_MIN -16.0
+#define LUMINANCE_HISTOGRAM_LOG2_MAX 16.0
+
+#endif  // NOTORIOUS6_LUMINANCE_HISTOGRAM_COMMON_GLSL
@@ -0,0 +1,186 @@
+/// Time constants of eye adaptation, in seconds; adapting to the dark is slower.
+#[derive(Clone, Copy)]
+pub struct AdaptationRates {
+    pub to_light: f64,
+    pub to_dark: f64,
+}
+
+impl Default for AdaptationRates {
+    fn default() -> Self {
+        Self {
+            to_light: 0.4,
+            to_dark: 4.0,
+        }
+    }
+}
+
+// How far from the frame being shown, in stops, counts as adapted to it.
+const ADAPTED_TOLERANCE: f64 = 0.01;
+
+/// Simulated eye adaptation: a log2 luminance which exponentially follows the log-average
+/// luminance of the frames shown, at different rates towards brighter and darker ones.
+pub struct Adaptation {
+    pub rates: AdaptationRates,
+    adapted: Option<f64>,
+    // Of the frame being shown.
+    target: Option<f64>,
+}
+
+impl Adaptation {
+    pub fn new(rates: AdaptationRates) -> Self {
+        Self {
+            rates,
+            adapted: None,
+            target: None,
+        }
+    }
+
+    /// What the next frame is seen with; `None` before the first one, which is seen fully adapted.
+    pub fn adapted_log2_luminance(&self) -> Option<f64> {
+        self.adapted
+    }
+
+    /// Forgets the frames shown so far.
+    pub fn reset(&mut self) {
+        self.adapted = None;
+        self.target = None;
+    }
+
+    /// Starts showing a frame with the log-average luminance `target`.
+    pub fn show(&mut self, target: f64) {
+        self.adapted.get_or_insert(target);
+        self.target = Some(target);
+    }
+
+    /// Adapts to the frame being shown, for `seconds`.
+    pub fn advance(&mut self, seconds: f64) {
+        let (adapted, target) = match (self.adapted, self.target) {
+            (Some(adapted), Some(target)) => (adapted, target),
+            _ => return,
+        };
+
+        let time_constant = if target > adapted {
+            self.rates.to_light
+        } else {
+            self.rates.to_dark
+        };
+        let t = if time_constant > 0.0 {
+            1.0 - (-seconds / time_constant).exp()
+        } else {
+            1.0
+        };
+
+        self.adapted = Some(adapted + (target - adapted) * t);
+    }
+
+    /// Whether it's still visibly away from the frame being shown.
+    pub fn is_adapting(&self) -> bool {
+        match (self.adapted, self.target) {
+            (Some(adapted), Some(target)) => (adapted - target).abs() > ADAPTED_TOLERANCE,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAP_AFTER_TIME_CONSTANT: f64 = 1.0 - 1.0 / std::f64::consts::E;
+
+    fn adapted_to(target: f64) -> Adaptation {
+        let mut adaptation = Adaptation::new(AdaptationRates::default());
+        adaptation.show(target);
+        adaptation
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn first_frame_is_seen_fully_adapted() {
+        let mut adaptation = Adaptation::new(AdaptationRates::default());
+        assert_eq!(adaptation.adapted_log2_luminance(), None);
+
+        adaptation.advance(1.0);
+        assert_eq!(adaptation.adapted_log2_luminance(), None);
+
+        adaptation.show(-3.0);
+        assert_eq!(adaptation.adapted_log2_luminance(), Some(-3.0));
+        assert!(!adaptation.is_adapting());
+    }
+
+    #[test]
+    fn adapts_to_light_at_its_rate() {
+        let mut adaptation = adapted_to(0.0);
+        adaptation.show(4.0);
+        assert!(adaptation.is_adapting());
+
+        adaptation.advance(adaptation.rates.to_light);
+        assert_close(
+            adaptation.adapted_log2_luminance().unwrap(),
+            4.0 * GAP_AFTER_TIME_CONSTANT,
+        );
+    }
+
+    #[test]
+    fn adapts_to_dark_at_its_rate() {
+        let mut adaptation = adapted_to(4.0);
+        adaptation.show(0.0);
+
+        adaptation.advance(adaptation.rates.to_dark);
+        assert_close(
+            adaptation.adapted_log2_luminance().unwrap(),
+            4.0 - 4.0 * GAP_AFTER_TIME_CONSTANT,
+        );
+
+        // The same time constant is a smaller step towards the dark.
+        let mut slow = adapted_to(4.0);
+        slow.show(0.0);
+        slow.advance(slow.rates.to_light);
+        assert!(slow.adapted_log2_luminance().unwrap() > 4.0 - 4.0 * GAP_AFTER_TIME_CONSTANT);
+    }
+
+    #[test]
+    fn reset_forgets_the_frames_shown() {
+        let mut adaptation = adapted_to(0.0);
+        adaptation.show(4.0);
+        adaptation.advance(0.1);
+
+        adaptation.reset();
+        assert_eq!(adaptation.adapted_log2_luminance(), None);
+        assert!(!adaptation.is_adapting());
+
+        adaptation.show(-2.0);
+        assert_eq!(adaptation.adapted_log2_luminance(), Some(-2.0));
+    }
+
+    #[test]
+    fn follows_a_sequence() {
+        // At 24 fps, a frame keeps exp(-1/24 / 0.4) = 0.9010751057 of the gap towards the light,
+        // and exp(-1/24 / 4) = 0.9896373989 of it towards the dark.
+        let expected = [
+            None,
+            Some(0.0),
+            // 3 - 3 * 0.9010751057
+            Some(0.2967746828),
+            // 3 - (3 - 0.2967746828) * 0.9010751057
+            Some(0.5641909615),
+            // -5 + (0.5641909615 + 5) * 0.9896373989
+            Some(0.5065314703),
+            // -5 + (0.5065314703 + 5) * 0.9896373989
+            Some(0.4494694813),
+        ];
+
+        let mut adaptation = Adaptation::new(AdaptationRates::default());
+        for (target, expected) in [0.0, 3.0, 3.0, -5.0, -5.0, 1.0].into_iter().zip(expected) {
+            adaptation.advance(1.0 / 24.0);
+            match (adaptation.adapted_log2_luminance(), expected) {
+                (Some(adapted), Some(expected)) => assert_close(adapted, expected),
+                (adapted, expected) => assert_eq!(adapted, expected),
+            }
+            adaptation.show(target);
+        }
+    }
+}
@@ -1,18 +1,47 @@
 use crate::{
+    adaptation::{Adaptation, AdaptationRates},
+    chromaticity::{ChromaticityDiagram, CHROMATICITY_OVERLAY_SIZE},
+    debug_view::DebugView,
+    external_lut::ExternalLut,
     fbo::Fbo,
+    frame_constants::{FrameConstants, TECHNIQUE_PARAM_COUNT},
+    histogram::{Histogram, HISTOGRAM_OVERLAY_SIZE},
+    hue_analysis::HueAnalysis,
+    icc_profile::display_profile,
+    image_diff::{log_diff_stats, write_diff_stats, DiffMetric, ImageDiffer},
+    image_loading::load_image,
     image_pool::*,
-    lut_lib::{AnyLutsChanged, LutDesc, LutLib},
+    image_saving::{read_framebuffer_rgb, write_exr, write_jpeg, write_png16, CaptureFormat},
+    image_stats::{ImageStats, IMAGE_STATS_BLOCK},
+    key_bindings::{Action, KeyBindings, KeyChord},
+    lut_bake::{linear_to_srgb, BakedLut, LutBakeDesc, LutDomain, LutFileFormat},
+    lut_dump::{dump_luts, LutDumpFormat},
+    lut_lib::{AnyLutsChanged, LutLib},
+    lut_view::{LutView, LUT_VIEW_OVERLAY_SIZE},
+    ocio::{ocio_lut_file_name, write_ocio_config, OCIO_LUT_FOLDER},
+    output_target::{DisplayPrimaries, OutputEncoding, OutputTarget},
+    overlay::{OverlayRect, OverlaySettings},
+    pass_graph::PassGraph,
+    provenance::{Provenance, ShaderRevision},
+    screenshot::ScreenshotNaming,
+    session::{Bookmark, Session, View},
     shader::ShaderKey,
     shader_lib::{AnyShadersChanged, ShaderLib},
     texture::Texture,
+    tone_curve::{ToneCurvePlot, TONE_CURVE_OVERLAY_SIZE},
+    vectorscope::{Vectorscope, VectorscopeMode, VECTORSCOPE_OVERLAY_SIZE},
+    waveform::{Waveform, WaveformMode, WAVEFORM_OVERLAY_SIZE},
 };
 use anyhow::Context;
-use glutin::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
-use jpeg_encoder::{ColorType, Encoder};
+use glutin::event::{
+    ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, WindowEvent,
+};
 use std::{
-    ffi::{c_void, CString},
+    collections::HashMap,
+    ffi::c_void,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Instant,
 };
 use turbosloth::LazyCache;
 
@@ -20,13 +49,112 @@ use turbosloth::LazyCache;
 struct InteractionState {
     dragging_ev: bool,
     last_cursor_position: [f64; 2],
+    modifiers: ModifiersState,
+    // Where the image was last drawn in the window: x, y from the top, width, and height.
+    image_rect: [i32; 4],
 }
 
 pub struct PendingImageCapture {
     ev: f64,
+    debug_view: DebugView,
     file_path: PathBuf,
     image_index: usize,
     shader_index: usize,
+    output_target: OutputTarget,
+    adaptation: CaptureAdaptation,
+}
+
+/// What the eye is adapted to in a capture.
+#[derive(Clone, Copy)]
+enum CaptureAdaptation {
+    /// The captured image.
+    Full,
+    /// A log2 luminance, as it was in the viewer.
+    To(f64),
+    /// The first frame of a batch sequence at this frame rate; seen fully adapted.
+    SequenceStart(f64),
+    /// A later frame of a batch sequence at this frame rate.
+    SequenceFrame(f64),
+}
+
+impl CaptureAdaptation {
+    fn sequence_frame_rate(self) -> Option<f64> {
+        match self {
+            CaptureAdaptation::SequenceStart(frame_rate)
+            | CaptureAdaptation::SequenceFrame(frame_rate) => Some(frame_rate),
+            CaptureAdaptation::Full | CaptureAdaptation::To(_) => None,
+        }
+    }
+}
+
+/// An entry in the Up/Down list: either a `.glsl` technique, or a `.cube` file
+/// applied by a shared shader.
+struct Technique {
+    shader: ShaderKey,
+    external_lut: Option<ExternalLut>,
+    passes: PassGraph,
+}
+
+impl Technique {
+    fn name(&self) -> String {
+        self.external_lut
+            .as_ref()
+            .map_or_else(|| self.shader.name(), |lut| lut.name.clone())
+    }
+
+    /// The `.glsl` or `.cube` file.
+    fn file(&self) -> &Path {
+        self.external_lut
+            .as_ref()
+            .map_or_else(|| self.shader.path(), |lut| lut.path.as_path())
+    }
+
+    /// Whether the final pass or any intermediate one reads `image_*` statistics.
+    fn uses_image_stats(&self, gl: &gl::Gl, shader_lib: &ShaderLib, shader_program: u32) -> bool {
+        shader_lib.uses_uniform_block(gl, shader_program, IMAGE_STATS_BLOCK)
+            || self
+                .passes
+                .uses_uniform_block(gl, shader_lib, IMAGE_STATS_BLOCK)
+    }
+
+    /// Of the final pass; `None` until it and every intermediate pass have compiled.
+    fn gl_handle(&self, shader_lib: &ShaderLib) -> Option<u32> {
+        shader_lib
+            .get_shader_gl_handle(&self.shader)
+            .filter(|_| self.passes.is_compiled(shader_lib))
+    }
+}
+
+pub struct PendingLutBake {
+    desc: LutBakeDesc,
+    file_path: PathBuf,
+    shader_index: usize,
+}
+
+pub struct PendingLutDump {
+    dir: PathBuf,
+    format: LutDumpFormat,
+}
+
+pub struct PendingAnalysis {
+    dir: PathBuf,
+    shader_index: usize,
+}
+
+/// What a technique is compared against by `diff`.
+#[derive(Clone)]
+enum DiffBaseline {
+    Technique(usize),
+    /// A display-linear image of the same size as the input, e.g. the output of another tool.
+    Reference(PathBuf),
+}
+
+pub struct PendingDiff {
+    baseline: DiffBaseline,
+    dir: PathBuf,
+    ev: f64,
+    image_index: usize,
+    shader_index: usize,
 }
 
 pub struct AppState {
@@ -36,10 +164,54 @@ pub struct AppState {
     lut_lib: LutLib,
     current_shader: usize,
     _lazy_cache: Arc<LazyCache>,
-    shaders: Vec<ShaderKey>,
+    // `GL_MAX_TEXTURE_SIZE`, which limits the LUTs that can be baked.
+    max_texture_size: usize,
+    shaders: Vec<Technique>,
     interaction: InteractionState,
+    histogram: Histogram,
+    waveform: Waveform,
+    vectorscope: Vectorscope,
+    chromaticity: ChromaticityDiagram,
+    lut_view: LutView,
+    tone_curve: ToneCurvePlot,
+    hue_analysis: HueAnalysis,
+    image_differ: ImageDiffer,
+    // Technique which the current one is compared against in the diff view.
+    diff_baseline: Option<usize>,
+    diff_view: Option<DiffMetric>,
+    // (image, technique, baseline) of the last diff whose stats were logged.
+    last_logged_diff: Option<(usize, usize, usize)>,
+    overlays: OverlaySettings,
+    image_stats: ImageStats,
+    // EV last used with each image, restored when switching back to it.
+    image_ev: HashMap<usize, f64>,
+    auto_exposure: bool,
+    needs_auto_exposure: bool,
+    pub debug_view: DebugView,
+    // Of the display the viewer renders for.
+    pub display_primaries: DisplayPrimaries,
+    bookmarks: Vec<Bookmark>,
+    pub key_bindings: KeyBindings,
+    pub screenshot_naming: ScreenshotNaming,
+    // Saved after the next frame is drawn.
+    pending_window_capture: Option<PathBuf>,
+    // Where the session is saved; `None` outside of the viewer.
+    pub session_file: Option<PathBuf>,
     pub pending_image_capture: Vec<PendingImageCapture>,
+    pub pending_lut_bake: Vec<PendingLutBake>,
+    pub pending_lut_dump: Option<PendingLutDump>,
+    pub pending_analysis: Vec<PendingAnalysis>,
+    pub pending_diff: Vec<PendingDiff>,
     pub ev: f64,
+    /// `frame_params` of every technique.
+    pub technique_params: [f32; TECHNIQUE_PARAM_COUNT],
+    start_time: Instant,
+    // Simulated in the viewer when enabled, and in batch sequences which ask for it.
+    // Off by default, since the viewer redraws every frame until adapted.
+    pub adaptation_enabled: bool,
+    adaptation: Adaptation,
+    batch_adaptation: Adaptation,
+    last_frame_time: Option<Instant>,
 }
 
 trait ModuloWrappingOps: Sized {
@@ -57,45 +229,80 @@ impl ModuloWrappingOps for usize {
     }
 }
 
+// Distance between overlays and the window edges, in physical pixels.
+const OVERLAY_MARGIN: i32 = 16;
+
 pub enum NeedsRedraw {
     Yes,
     No,
 }
 
 impl AppState {
-    pub fn new(input_path: PathBuf, gl: &gl::Gl) -> anyhow::Result<Self> {
+    /// `.cube` files which don't record their input domain are assumed to use `external_lut_domain`.
+    pub fn new(
+        input_path: PathBuf,
+        external_lut_domain: LutDomain,
+        gl: &gl::Gl,
+    ) -> anyhow::Result<Self> {
         let image_pool = ImagePool::new(input_path)?;
         let lazy_cache = LazyCache::create();
 
         let mut shader_lib = ShaderLib::new(&lazy_cache, gl);
 
         let shaders_folder = "shaders";
-        let shaders: Vec<ShaderKey> = std::fs::read_dir(shaders_folder)
+        let mut shaders: Vec<Technique> = std::fs::read_dir(shaders_folder)
             .context("Reading the shaders/ directory")?
             .filter_map(|entry| {
                 let path = entry.ok()?.path();
-                (path.is_file() && path.extension() == Some(std::ffi::OsStr::new("glsl"))).then(
-                    || {
-                        shader_lib.add_shader(format!(
-                            "{}/{}",
-                            shaders_folder,
-                            path.file_name().unwrap().to_string_lossy()
-                        ))
-                    },
-                )
+                if !(path.is_file() && path.extension() == Some(std::ffi::OsStr::new("glsl"))) {
+                    return None;
+                }
+
+                // Kept when invalid, so that it can be fixed while the viewer runs.
+                let mut passes = PassGraph::default();
+                if let Err(err) = passes.load(gl, &path, &mut shader_lib) {
+                    log::error!("Invalid pass declaration in {:?}: {:?}", path, err);
+                }
+
+                Some(Technique {
+                    shader: shader_lib.add_shader(format!(
+                        "{}/{}",
+                        shaders_folder,
+                        path.file_name().unwrap().to_string_lossy()
+                    )),
+                    external_lut: None,
+                    passes,
+                })
             })
             .collect();
 
+        let external_lut_shader = shader_lib.add_shader("shaders/external_lut/apply.glsl");
+        for entry in std::fs::read_dir(shaders_folder)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension() == Some(std::ffi::OsStr::new("cube")) {
+                match ExternalLut::load(gl, &path, external_lut_domain) {
+                    Ok(lut) => shaders.push(Technique {
+                        shader: external_lut_shader.clone(),
+                        external_lut: Some(lut),
+                        passes: Default::default(),
+                    }),
+                    Err(err) => log::error!("Failed to load {:?}: {:?}", path, err),
+                }
+            }
+        }
+
+        let histogram = Histogram::new(&mut shader_lib, gl);
+        let waveform = Waveform::new(&mut shader_lib, gl);
+        let vectorscope = Vectorscope::new(&mut shader_lib, gl);
+        let chromaticity = ChromaticityDiagram::new(&mut shader_lib, gl);
+        let image_stats = ImageStats::new(&mut shader_lib, gl);
+        let lut_view = LutView::new(&mut shader_lib);
+        let tone_curve = ToneCurvePlot::new(&mut shader_lib, gl);
+        let hue_analysis = HueAnalysis::new(&mut shader_lib, gl);
+        let image_differ = ImageDiffer::new(&mut shader_lib);
+
         let mut lut_lib = LutLib::new(&lazy_cache);
-        lut_lib.add_lut(
-            LutDesc {
-                width: 64,
-                internal_format: gl::RG32F,
-                name: "bezold_brucke_lut".into(),
-                shader_path: "shaders/lut/bezold_brucke_lut.glsl".into(),
-            },
-            gl,
-        );
+        lut_lib.add_luts_from_folder("shaders/lut", gl)?;
 
         Ok(Self {
             image_pool,
@@ -104,10 +311,48 @@ impl AppState {
             lut_lib,
             current_shader: 0,
             _lazy_cache: lazy_cache,
+            max_texture_size: {
+                let mut max_texture_size = 0;
+                unsafe { gl.GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size) };
+                max_texture_size as usize
+            },
             shaders,
             interaction: Default::default(),
+            histogram,
+            waveform,
+            vectorscope,
+            chromaticity,
+            lut_view,
+            tone_curve,
+            hue_analysis,
+            image_differ,
+            diff_baseline: None,
+            diff_view: None,
+            last_logged_diff: None,
+            overlays: Default::default(),
+            image_stats,
+            image_ev: Default::default(),
+            auto_exposure: false,
+            needs_auto_exposure: false,
+            debug_view: Default::default(),
+            display_primaries: DisplayPrimaries::Bt709,
+            bookmarks: Vec::new(),
+            key_bindings: Default::default(),
+            screenshot_naming: Default::default(),
+            pending_window_capture: None,
+            session_file: None,
             pending_image_capture: Default::default(),
+            pending_lut_bake: Default::default(),
+            pending_lut_dump: None,
+            pending_analysis: Default::default(),
+            pending_diff: Default::default(),
             ev: 0.0,
+            technique_params: [0.0; TECHNIQUE_PARAM_COUNT],
+            start_time: Instant::now(),
+            adaptation_enabled: false,
+            adaptation: Adaptation::new(Default::default()),
+            batch_adaptation: Adaptation::new(Default::default()),
+            last_frame_time: None,
         })
     }
 
@@ -115,8 +360,18 @@ impl AppState {
         let luts_changed = self.lut_lib.compile_all(gl);
         let shaders_changed = self.shader_lib.compile_all(gl);
 
+        if matches!(shaders_changed, AnyShadersChanged::Yes) {
+            self.reload_pass_declarations(gl);
+        }
+        let exposure_changed = self.apply_pending_auto_exposure(gl);
+
+        // Redrawn continuously until adapted.
+        let adapting = self.adaptation_enabled && self.adaptation.is_adapting();
+
         if matches!(luts_changed, AnyLutsChanged::Yes)
             || matches!(shaders_changed, AnyShadersChanged::Yes)
+            || exposure_changed
+            || adapting
         {
             NeedsRedraw::Yes
         } else {
@@ -124,44 +379,465 @@ impl AppState {
         }
     }
 
+    /// Picks up changes to the `// pass:` declarations of reloaded techniques. New pass shaders
+    /// compile on the next update.
+    fn reload_pass_declarations(&mut self, gl: &gl::Gl) {
+        for technique in &mut self.shaders {
+            if technique.external_lut.is_some() {
+                continue;
+            }
+
+            let path = technique.shader.path().to_owned();
+            if let Err(err) = technique.passes.load(gl, &path, &mut self.shader_lib) {
+                log::error!("Invalid pass declaration in {:?}: {:?}", path, err);
+            }
+        }
+    }
+
+    pub fn set_adaptation_rates(&mut self, rates: AdaptationRates) {
+        self.adaptation.rates = rates;
+        self.batch_adaptation.rates = rates;
+    }
+
+    pub fn set_auto_exposure(&mut self, enabled: bool) {
+        self.auto_exposure = enabled;
+        self.needs_auto_exposure = enabled;
+    }
+
+    fn switch_image(&mut self, image_index: usize) {
+        self.image_ev.insert(self.current_image, self.ev);
+        self.current_image = image_index;
+
+        if let Some(&ev) = self.image_ev.get(&image_index) {
+            self.ev = ev;
+        } else if self.auto_exposure {
+            self.needs_auto_exposure = true;
+        }
+    }
+
+    // Returns true if the EV was changed.
+    fn apply_pending_auto_exposure(&mut self, gl: &gl::Gl) -> bool {
+        if !self.needs_auto_exposure {
+            return false;
+        }
+
+        let texture = match self.image_pool.get_texture(self.current_image, gl) {
+            Some(texture) => texture,
+            None => {
+                // Failed to load; nothing to expose.
+                self.needs_auto_exposure = false;
+                return false;
+            }
+        };
+
+        // Retried on the next update if the shader isn't compiled yet.
+        if let Some(stats) =
+            self.image_stats
+                .statistics(gl, &self.shader_lib, texture, Some(self.current_image))
+        {
+            self.needs_auto_exposure = false;
+
+            if let Some(ev) = stats.histogram.auto_exposure_ev() {
+                self.ev = ev;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn has_pending_requests(&self) -> bool {
+        !self.pending_image_capture.is_empty()
+            || !self.pending_lut_bake.is_empty()
+            || self.pending_lut_dump.is_some()
+            || !self.pending_analysis.is_empty()
+            || !self.pending_diff.is_empty()
+    }
+
     pub fn process_batched_requests(&mut self, gl: &gl::Gl) -> anyhow::Result<()> {
+        if let Some(pending) = self.pending_lut_dump.take() {
+            dump_luts(&self.lut_lib, gl, &pending.dir, pending.format)?;
+        }
+
+        // The same for every capture of a technique; `git` is too slow to ask per image.
+        let mut shader_revisions: HashMap<PathBuf, Option<ShaderRevision>> = HashMap::new();
+
         for pending in self.pending_image_capture.drain(..) {
+            let source_image = self
+                .image_pool
+                .get_image_path(pending.image_index)
+                .cloned()
+                .unwrap_or_default();
             let texture = match self.image_pool.get_texture(pending.image_index, gl) {
                 Some(texture) => texture,
                 None => continue,
             };
 
-            let fbo = Fbo::new(gl, texture.size);
+            let format = CaptureFormat::from_path(&pending.file_path)?;
+            let fbo = Fbo::with_format(gl, texture.size, format.framebuffer_format());
             fbo.bind(gl);
 
-            let shader = self
-                .shader_lib
-                .get_shader_gl_handle(&self.shaders[pending.shader_index])
+            let technique = &self.shaders[pending.shader_index];
+            let shader = technique
+                .gl_handle(&self.shader_lib)
                 .expect("get_shader_gl_handle");
 
-            draw_texture(gl, texture, shader, texture.size, pending.ev, &self.lut_lib);
-            Self::capture_screenshot(gl, texture, &pending.file_path)?;
+            let adapted_log2_luminance = match pending.adaptation {
+                CaptureAdaptation::Full => None,
+                CaptureAdaptation::To(adapted) => Some(adapted),
+                CaptureAdaptation::SequenceStart(_) => {
+                    self.batch_adaptation.reset();
+                    None
+                }
+                CaptureAdaptation::SequenceFrame(frame_rate) => {
+                    self.batch_adaptation.advance(1.0 / frame_rate);
+                    self.batch_adaptation.adapted_log2_luminance()
+                }
+            };
+
+            draw_texture(
+                gl,
+                texture,
+                &self.shader_lib,
+                shader,
+                FrameConstants {
+                    adapted_log2_luminance: adapted_log2_luminance.map_or(f32::NAN, |v| v as f32),
+                    ..FrameConstants::new(
+                        pending.ev,
+                        Some(pending.image_index),
+                        self.technique_params,
+                    )
+                },
+                pending.debug_view,
+                &self.lut_lib,
+                &self.image_stats,
+                technique,
+                pending.output_target,
+            );
+
+            let adaptation_frame_rate = pending.adaptation.sequence_frame_rate();
+            if adaptation_frame_rate.is_some() {
+                if let Some(stats) = self.image_stats.statistics(
+                    gl,
+                    &self.shader_lib,
+                    texture,
+                    Some(pending.image_index),
+                ) {
+                    self.batch_adaptation
+                        .show(stats.log2_average() + pending.ev);
+                }
+            }
+
+            let provenance = Provenance {
+                technique: technique.name(),
+                technique_file: technique.file().to_owned(),
+                source_image,
+                ev: pending.ev,
+                debug_view: pending.debug_view,
+                output_target: pending.output_target,
+                params: self.technique_params,
+                adaptation_frame_rate,
+                adaptation_rates: self.batch_adaptation.rates,
+                shader_revision: shader_revisions
+                    .entry(technique.file().to_owned())
+                    .or_insert_with(|| ShaderRevision::of(technique.file()))
+                    .clone(),
+            };
+
+            match format {
+                CaptureFormat::Jpeg => Self::capture_screenshot(
+                    gl,
+                    texture.size,
+                    &pending.file_path,
+                    pending.output_target.primaries,
+                    Some(&provenance),
+                )?,
+                CaptureFormat::Png | CaptureFormat::Exr => {
+                    if let Some(parent_dir) = pending.file_path.parent() {
+                        std::fs::create_dir_all(parent_dir)?;
+                    }
+
+                    let mut pixels = read_framebuffer_rgb(gl, texture.size);
+                    if format == CaptureFormat::Png {
+                        // The sRGB and scRGB targets are left linear in the framebuffer.
+                        if !pending.output_target.is_encoded() {
+                            for px in &mut pixels {
+                                *px = px.map(|v| linear_to_srgb(v.clamp(0.0, 1.0)));
+                            }
+                        }
+                        // HDR is described by `cICP` alone.
+                        let icc_profile = (!pending.output_target.is_hdr())
+                            .then(|| display_profile(pending.output_target.primaries));
+                        write_png16(
+                            &pending.file_path,
+                            texture.size,
+                            &pixels,
+                            pending.output_target.cicp(),
+                            icc_profile.as_deref(),
+                            Some(&provenance),
+                        )?;
+                    } else {
+                        write_exr(
+                            &pending.file_path,
+                            texture.size,
+                            &pixels,
+                            pending.output_target.primaries.chromaticities(),
+                            Some(&provenance),
+                        )?;
+                    }
+                }
+            }
             log::info!("Saved {:?}", pending.file_path);
 
             fbo.destroy(gl);
         }
 
+        for pending in self.pending_lut_bake.drain(..) {
+            let technique = &self.shaders[pending.shader_index];
+            let shader = technique.gl_handle(&self.shader_lib).ok_or_else(|| {
+                anyhow::anyhow!("Shader {:?} failed to compile", technique.name())
+            })?;
+
+            if technique.uses_image_stats(gl, &self.shader_lib, shader) {
+                log::warn!(
+                    "{:?} reads image statistics, which a LUT can't capture; the bake uses those of the LUT lattice",
+                    technique.name()
+                );
+            }
+
+            let lattice = Texture::new_2d(gl, &pending.desc.lattice_image());
+
+            // Float, so that the output isn't quantized before it's written out.
+            let fbo = Fbo::with_format(gl, lattice.size, gl::RGBA32F);
+            fbo.bind(gl);
+
+            draw_texture(
+                gl,
+                &lattice,
+                &self.shader_lib,
+                shader,
+                FrameConstants::new(pending.desc.ev, None, self.technique_params),
+                DebugView::None,
+                &self.lut_lib,
+                &self.image_stats,
+                technique,
+                OutputTarget::SRGB,
+            );
+
+            fbo.bind_read(gl);
+            let baked = BakedLut::read_from_framebuffer(gl, pending.desc, technique.name());
+            fbo.unbind_read(gl);
+
+            fbo.destroy(gl);
+            self.image_stats.forget(&lattice);
+            lattice.destroy(gl);
+
+            baked.write(&pending.file_path)?;
+            log::info!("Saved {:?}", pending.file_path);
+        }
+
+        for pending in self.pending_analysis.drain(..) {
+            let technique = &self.shaders[pending.shader_index];
+            let shader = technique.gl_handle(&self.shader_lib).ok_or_else(|| {
+                anyhow::anyhow!("Shader {:?} failed to compile", technique.name())
+            })?;
+
+            let lut_lib = &self.lut_lib;
+            let shader_lib = &self.shader_lib;
+            let image_stats = &self.image_stats;
+            let frame = FrameConstants::new(0.0, None, self.technique_params);
+            let report = self
+                .hue_analysis
+                .run(gl, shader_lib, technique.name(), |grid| {
+                    draw_texture(
+                        gl,
+                        grid,
+                        shader_lib,
+                        shader,
+                        frame,
+                        DebugView::None,
+                        lut_lib,
+                        image_stats,
+                        technique,
+                        OutputTarget::SRGB,
+                    );
+                    image_stats.forget(grid);
+                })?;
+
+            report.write(&pending.dir)?;
+        }
+
+        for pending in std::mem::take(&mut self.pending_diff) {
+            let texture = match self.image_pool.get_texture(pending.image_index, gl) {
+                Some(texture) => *texture,
+                None => continue,
+            };
+            let image_name = self
+                .image_pool
+                .get_image_path(pending.image_index)
+                .and_then(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+                .unwrap_or_else(|| pending.image_index.to_string());
+            let frame =
+                FrameConstants::new(pending.ev, Some(pending.image_index), self.technique_params);
+
+            let output = self
+                .render_technique(gl, &texture, pending.shader_index, frame)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Shader {:?} failed to compile",
+                        self.shaders[pending.shader_index].name()
+                    )
+                })?;
+
+            let (baseline, baseline_fbo) = match &pending.baseline {
+                DiffBaseline::Technique(shader_index) => {
+                    let fbo = self
+                        .render_technique(gl, &texture, *shader_index, frame)
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "Shader {:?} failed to compile",
+                                self.shaders[*shader_index].name()
+                            )
+                        })?;
+                    (fbo.texture, Some(fbo))
+                }
+                DiffBaseline::Reference(path) => {
+                    let image = load_image(path)?;
+                    anyhow::ensure!(
+                        image.size == texture.size,
+                        "The reference image {:?} is {:?}, but {:?} is {:?}",
+                        path,
+                        image.size,
+                        image_name,
+                        texture.size
+                    );
+                    (Texture::new_2d(gl, &image), None)
+                }
+            };
+
+            let diff = self
+                .image_differ
+                .compute(
+                    gl,
+                    &self.shader_lib,
+                    &output.texture,
+                    &baseline,
+                    baseline_fbo.is_none(),
+                )
+                .context("The image diff shader failed to compile")?;
+            let stats = diff.stats(gl);
+
+            output.bind(gl);
+            for metric in DiffMetric::ALL {
+                let file_path =
+                    pending
+                        .dir
+                        .join(format!("{}.{}.jpg", image_name, metric.short_name()));
+                self.image_differ
+                    .draw_heatmap(gl, &self.shader_lib, &diff, metric);
+                Self::capture_screenshot(
+                    gl,
+                    texture.size,
+                    &file_path,
+                    DisplayPrimaries::Bt709,
+                    None,
+                )?;
+                log::info!("Saved {:?}", file_path);
+            }
+
+            let stats_path = pending.dir.join(format!("{}.csv", image_name));
+            write_diff_stats(&stats, &stats_path)?;
+            log::info!("Saved {:?}", stats_path);
+            log_diff_stats(&image_name, &stats);
+
+            diff.destroy(gl);
+            match baseline_fbo {
+                Some(fbo) => fbo.destroy(gl),
+                None => baseline.destroy(gl),
+            }
+            output.destroy(gl);
+        }
+
         Ok(())
     }
 
     pub fn draw_frame(&mut self, gl: &gl::Gl, physical_window_size: [usize; 2]) {
-        let texture = self.image_pool.get_texture(self.current_image, gl);
+        let texture = self.image_pool.get_texture(self.current_image, gl).copied();
 
         unsafe {
-            let shader = self
-                .shader_lib
-                .get_shader_gl_handle(&self.shaders[self.current_shader]);
+            let technique = &self.shaders[self.current_shader];
+            let shader = technique.gl_handle(&self.shader_lib);
 
             if let Some((texture, shader)) = texture.zip(shader) {
+                // Adapted to the previous frame for as long as it was shown.
+                let now = Instant::now();
+                if !self.adaptation_enabled {
+                    self.adaptation.reset();
+                } else if let Some(last_frame_time) = self.last_frame_time {
+                    self.adaptation
+                        .advance((now - last_frame_time).as_secs_f64());
+                }
+                self.last_frame_time = Some(now);
+
+                let frame = self.viewer_frame_constants();
+
+                if self.overlays.tone_curve {
+                    let lut_lib = &self.lut_lib;
+                    let shader_lib = &self.shader_lib;
+                    let image_stats = &self.image_stats;
+                    let ramp_frame = FrameConstants::new(0.0, None, self.technique_params);
+                    self.tone_curve.evaluate(gl, technique.name(), |ramp| {
+                        draw_texture(
+                            gl,
+                            ramp,
+                            shader_lib,
+                            shader,
+                            ramp_frame,
+                            DebugView::None,
+                            lut_lib,
+                            image_stats,
+                            technique,
+                            OutputTarget::SRGB,
+                        )
+                    });
+                }
+
                 let fbo = Fbo::new(gl, texture.size);
                 fbo.bind(gl);
 
-                draw_texture(gl, texture, shader, texture.size, self.ev, &self.lut_lib);
+                draw_texture(
+                    gl,
+                    &texture,
+                    &self.shader_lib,
+                    shader,
+                    frame,
+                    self.debug_view,
+                    &self.lut_lib,
+                    &self.image_stats,
+                    technique,
+                    // The heatmap replaces the output, and ΔE is measured in BT.709.
+                    if self.diff_view.is_some() {
+                        OutputTarget::SRGB
+                    } else {
+                        self.viewer_output_target()
+                    },
+                );
+                self.compute_scopes(gl, &texture, &fbo.texture);
+
+                if self.adaptation_enabled {
+                    if let Some(stats) = self.image_stats.statistics(
+                        gl,
+                        &self.shader_lib,
+                        &texture,
+                        Some(self.current_image),
+                    ) {
+                        self.adaptation.show(stats.log2_average() + self.ev);
+                    }
+                }
+
+                if let Some(metric) = self.diff_view {
+                    self.draw_diff_heatmap(gl, &texture, &fbo, frame, metric);
+                }
 
                 let width_frac: f64 = texture.size[0] as f64 / physical_window_size[0] as f64;
                 let height_frac: f64 = texture.size[1] as f64 / physical_window_size[1] as f64;
@@ -173,6 +849,8 @@ impl AppState {
                 let height = (physical_window_size[1] as f64 * height_frac) as i32;
                 let x_offset = (physical_window_size[0] as i32 - width) / 2;
                 let y_offset = (physical_window_size[1] as i32 - height) / 2;
+                // Centered, so the offset from the top is the same as from the bottom.
+                self.interaction.image_rect = [x_offset, y_offset, width, height];
 
                 fbo.unbind(gl);
                 fbo.bind_read(gl);
@@ -194,6 +872,12 @@ impl AppState {
 
                 fbo.unbind_read(gl);
                 fbo.destroy(gl);
+
+                self.draw_scopes(gl);
+
+                if let Some(file_path) = self.pending_window_capture.take() {
+                    self.capture_window(gl, physical_window_size, &file_path);
+                }
             } else {
                 if shader.is_none() {
                     gl.ClearColor(0.5, 0.0, 0.0, 1.0);
@@ -206,8 +890,242 @@ impl AppState {
         }
     }
 
-    fn capture_screenshot(gl: &gl::Gl, texture: &Texture, file_path: &Path) -> anyhow::Result<()> {
-        let mut pixels = vec![0u8; texture.size.into_iter().product::<usize>() * 4];
+    fn next_screenshot_path(&self, format: CaptureFormat) -> PathBuf {
+        let image_name = self
+            .image_pool
+            .get_image_path(self.current_image)
+            .and_then(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+            .unwrap_or_default();
+
+        self.screenshot_naming.next_path(
+            &image_name,
+            &self.current_shader(),
+            self.ev,
+            format.extension(),
+        )
+    }
+
+    /// Saves the window contents, with the overlays, as drawn by the last `draw_frame`.
+    fn capture_window(&self, gl: &gl::Gl, physical_window_size: [usize; 2], file_path: &Path) {
+        let technique = &self.shaders[self.current_shader];
+        let provenance = Provenance {
+            technique: technique.name(),
+            technique_file: technique.file().to_owned(),
+            source_image: self
+                .image_pool
+                .get_image_path(self.current_image)
+                .cloned()
+                .unwrap_or_default(),
+            ev: self.ev,
+            debug_view: self.debug_view,
+            output_target: self.viewer_output_target(),
+            params: self.technique_params,
+            adaptation_frame_rate: None,
+            adaptation_rates: self.adaptation.rates,
+            shader_revision: ShaderRevision::of(technique.file()),
+        };
+
+        match Self::capture_screenshot(
+            gl,
+            physical_window_size,
+            file_path,
+            self.display_primaries,
+            Some(&provenance),
+        ) {
+            Ok(()) => log::info!("Saved {:?}", file_path),
+            Err(err) => log::error!("Failed to save {:?}: {:?}", file_path, err),
+        }
+    }
+
+    /// SDR output for the display the viewer runs on.
+    fn viewer_output_target(&self) -> OutputTarget {
+        OutputTarget {
+            encoding: OutputEncoding::Srgb,
+            primaries: self.display_primaries,
+        }
+    }
+
+    /// Of the image on screen, with the time, and where the cursor is over it.
+    fn viewer_frame_constants(&self) -> FrameConstants {
+        let [cursor_x, cursor_y] = self.interaction.last_cursor_position;
+        let [x, y, width, height] = self.interaction.image_rect.map(f64::from);
+        let cursor_uv = [(cursor_x - x) / width, (cursor_y - y) / height];
+
+        FrameConstants {
+            cursor_uv: if cursor_uv.iter().all(|uv| (0.0..=1.0).contains(uv)) {
+                cursor_uv.map(|uv| uv as f32)
+            } else {
+                [-1.0; 2]
+            },
+            time: self.start_time.elapsed().as_secs_f32(),
+            adapted_log2_luminance: self
+                .adaptation
+                .adapted_log2_luminance()
+                .map_or(f32::NAN, |adapted| adapted as f32),
+            ..FrameConstants::new(self.ev, Some(self.current_image), self.technique_params)
+        }
+    }
+
+    /// Renders the technique at `shader_index` into a new framebuffer the size of `texture`.
+    fn render_technique(
+        &self,
+        gl: &gl::Gl,
+        texture: &Texture,
+        shader_index: usize,
+        frame: FrameConstants,
+    ) -> Option<Fbo> {
+        let technique = &self.shaders[shader_index];
+        let shader = technique.gl_handle(&self.shader_lib)?;
+
+        let fbo = Fbo::new(gl, texture.size);
+        fbo.bind(gl);
+        draw_texture(
+            gl,
+            texture,
+            &self.shader_lib,
+            shader,
+            frame,
+            self.debug_view,
+            &self.lut_lib,
+            &self.image_stats,
+            technique,
+            OutputTarget::SRGB,
+        );
+
+        Some(fbo)
+    }
+
+    // Replaces the output in `fbo` with a heatmap of its difference from the baseline technique.
+    fn draw_diff_heatmap(
+        &mut self,
+        gl: &gl::Gl,
+        texture: &Texture,
+        fbo: &Fbo,
+        frame: FrameConstants,
+        metric: DiffMetric,
+    ) {
+        let baseline_index = match self.diff_baseline {
+            Some(baseline_index) => baseline_index,
+            None => return,
+        };
+
+        let baseline = match self.render_technique(gl, texture, baseline_index, frame) {
+            Some(baseline) => baseline,
+            None => return,
+        };
+
+        if let Some(diff) =
+            self.image_differ
+                .compute(gl, &self.shader_lib, &fbo.texture, &baseline.texture, false)
+        {
+            fbo.bind(gl);
+            self.image_differ
+                .draw_heatmap(gl, &self.shader_lib, &diff, metric);
+
+            // Once per comparison rather than every frame, as it reads back the whole image.
+            let key = (self.current_image, self.current_shader, baseline_index);
+            if self.last_logged_diff != Some(key) {
+                self.last_logged_diff = Some(key);
+                log_diff_stats(
+                    &format!(
+                        "{:?} vs {:?}",
+                        self.current_shader(),
+                        self.shaders[baseline_index].name()
+                    ),
+                    &diff.stats(gl),
+                );
+            }
+
+            diff.destroy(gl);
+        }
+
+        fbo.bind(gl);
+        baseline.destroy(gl);
+    }
+
+    fn compute_scopes(&mut self, gl: &gl::Gl, input: &Texture, output: &Texture) {
+        if self.overlays.histogram {
+            self.histogram
+                .compute(gl, &self.shader_lib, input, self.ev, output);
+        }
+
+        if self.overlays.waveform.is_some() {
+            self.waveform.compute(gl, &self.shader_lib, output);
+        }
+
+        if let Some(mode) = self.overlays.vectorscope {
+            self.vectorscope.compute(gl, &self.shader_lib, mode, output);
+        }
+
+        if self.overlays.chromaticity {
+            self.chromaticity
+                .compute(gl, &self.shader_lib, input, output);
+        }
+    }
+
+    // Lays out the enabled scopes left to right along the bottom of the window.
+    fn draw_scopes(&self, gl: &gl::Gl) {
+        let mut x = OVERLAY_MARGIN;
+        let mut next_rect = |size: [i32; 2]| {
+            let rect = OverlayRect {
+                x,
+                y: OVERLAY_MARGIN,
+                width: size[0],
+                height: size[1],
+            };
+            x += size[0] + OVERLAY_MARGIN;
+            rect
+        };
+
+        if self.overlays.histogram {
+            self.histogram
+                .draw(gl, &self.shader_lib, next_rect(HISTOGRAM_OVERLAY_SIZE));
+        }
+
+        if let Some(mode) = self.overlays.waveform {
+            self.waveform
+                .draw(gl, &self.shader_lib, mode, next_rect(WAVEFORM_OVERLAY_SIZE));
+        }
+
+        if let Some(mode) = self.overlays.vectorscope {
+            self.vectorscope.draw(
+                gl,
+                &self.shader_lib,
+                mode,
+                next_rect(VECTORSCOPE_OVERLAY_SIZE),
+            );
+        }
+
+        if self.overlays.chromaticity {
+            self.chromaticity
+                .draw(gl, &self.shader_lib, next_rect(CHROMATICITY_OVERLAY_SIZE));
+        }
+
+        if self.overlays.tone_curve {
+            self.tone_curve
+                .draw(gl, &self.shader_lib, next_rect(TONE_CURVE_OVERLAY_SIZE));
+        }
+
+        if let Some(lut_index) = self.overlays.lut {
+            self.lut_view.draw(
+                gl,
+                &self.shader_lib,
+                &self.lut_lib,
+                lut_index,
+                next_rect(LUT_VIEW_OVERLAY_SIZE),
+            );
+        }
+    }
+
+    /// Saves the bound SDR framebuffer as a JPEG, tagged with `primaries`.
+    fn capture_screenshot(
+        gl: &gl::Gl,
+        size: [usize; 2],
+        file_path: &Path,
+        primaries: DisplayPrimaries,
+        provenance: Option<&Provenance>,
+    ) -> anyhow::Result<()> {
+        let mut pixels = vec![0u8; size.into_iter().product::<usize>() * 4];
 
         if let Some(parent_dir) = file_path.parent() {
             std::fs::create_dir_all(parent_dir)?;
@@ -218,8 +1136,8 @@ impl AppState {
             gl.ReadPixels(
                 0,
                 0,
-                texture.size[0] as _,
-                texture.size[1] as _,
+                size[0] as _,
+                size[1] as _,
                 gl::RGBA,
                 gl::UNSIGNED_BYTE,
                 pixels.as_mut_ptr() as *mut c_void,
@@ -229,7 +1147,7 @@ impl AppState {
         // Flip it
         {
             let mut pixels = pixels.as_mut_slice();
-            let row_bytes = texture.size[0] * 4;
+            let row_bytes = size[0] * 4;
             while pixels.len() >= row_bytes * 2 {
                 let (a, rest) = pixels.split_at_mut(row_bytes);
                 pixels = rest;
@@ -239,20 +1157,13 @@ impl AppState {
             }
         }
 
-        // Create new encoder that writes to a file with maximum quality (100)
-        let encoder = Encoder::new_file(file_path, 90)
-            .with_context(|| format!("Failed to create {:?}", file_path))?;
-
-        encoder
-            .encode(
-                &pixels,
-                texture.size[0] as _,
-                texture.size[1] as _,
-                ColorType::Rgba,
-            )
-            .context("encoder.encode")?;
-
-        Ok(())
+        write_jpeg(
+            file_path,
+            size,
+            &pixels,
+            &display_profile(primaries),
+            provenance,
+        )
     }
 
     fn handle_keyboard_input(&mut self, input: KeyboardInput) -> NeedsRedraw {
@@ -260,40 +1171,197 @@ impl AppState {
             return NeedsRedraw::No;
         }
 
-        match input.virtual_keycode {
-            Some(VirtualKeyCode::Left) => {
-                self.current_image = self
-                    .current_image
-                    .modulo_wrapping_dec(self.image_pool.image_count());
+        let action = input.virtual_keycode.and_then(|key| {
+            self.key_bindings
+                .action(KeyChord::new(key, self.interaction.modifiers))
+        });
+
+        match action {
+            Some(action) => self.perform_action(action),
+            None => NeedsRedraw::No,
+        }
+    }
+
+    fn perform_action(&mut self, action: Action) -> NeedsRedraw {
+        match action {
+            Action::PrevImage => {
+                self.switch_image(
+                    self.current_image
+                        .modulo_wrapping_dec(self.image_pool.image_count()),
+                );
                 NeedsRedraw::Yes
             }
-            Some(VirtualKeyCode::Right) => {
-                self.current_image = self
-                    .current_image
-                    .modulo_wrapping_inc(self.image_pool.image_count());
+            Action::NextImage => {
+                self.switch_image(
+                    self.current_image
+                        .modulo_wrapping_inc(self.image_pool.image_count()),
+                );
                 NeedsRedraw::Yes
             }
-            Some(VirtualKeyCode::Up) => {
+            Action::NextTechnique => {
                 self.current_shader = self.current_shader.modulo_wrapping_inc(self.shaders.len());
                 NeedsRedraw::Yes
             }
-            Some(VirtualKeyCode::Down) => {
+            Action::PrevTechnique => {
                 self.current_shader = self.current_shader.modulo_wrapping_dec(self.shaders.len());
                 NeedsRedraw::Yes
             }
-            Some(VirtualKeyCode::F12) => {
+            Action::ToggleAutoExposure => {
+                // Also re-exposes the current image when enabled.
+                self.set_auto_exposure(!self.auto_exposure);
+                log::info!("Auto-exposure: {}", self.auto_exposure);
+                NeedsRedraw::No
+            }
+            Action::ToggleAdaptation => {
+                self.adaptation_enabled = !self.adaptation_enabled;
+                log::info!("Eye adaptation: {}", self.adaptation_enabled);
+                NeedsRedraw::Yes
+            }
+            Action::ToggleHistogram => {
+                self.overlays.histogram = !self.overlays.histogram;
+                NeedsRedraw::Yes
+            }
+            Action::CycleWaveform => {
+                self.overlays.waveform = match self.overlays.waveform {
+                    None => Some(WaveformMode::Luma),
+                    Some(WaveformMode::Luma) => Some(WaveformMode::RgbParade),
+                    Some(WaveformMode::RgbParade) => None,
+                };
+                NeedsRedraw::Yes
+            }
+            Action::CycleVectorscope => {
+                self.overlays.vectorscope = match self.overlays.vectorscope {
+                    None => Some(VectorscopeMode::YCbCr),
+                    Some(VectorscopeMode::YCbCr) => Some(VectorscopeMode::Oklab),
+                    Some(VectorscopeMode::Oklab) => None,
+                };
+                NeedsRedraw::Yes
+            }
+            Action::ToggleChromaticity => {
+                self.overlays.chromaticity = !self.overlays.chromaticity;
+                NeedsRedraw::Yes
+            }
+            Action::ToggleToneCurve => {
+                self.overlays.tone_curve = !self.overlays.tone_curve;
+                NeedsRedraw::Yes
+            }
+            Action::PinToneCurve => {
+                if let Some(pinned) = self.tone_curve.toggle_pin_current() {
+                    log::info!(
+                        "Tone curve of {:?} {}",
+                        self.current_shader(),
+                        if pinned { "pinned" } else { "unpinned" }
+                    );
+                }
+                NeedsRedraw::Yes
+            }
+            Action::CycleLutPlot => {
+                let next = self.overlays.lut.map_or(0, |lut_index| lut_index + 1);
+                self.overlays.lut = (next < self.lut_lib.lut_count()).then_some(next);
+
+                if let Some((desc, _, stats)) = self.overlays.lut.and_then(|i| self.lut_lib.get(i))
+                {
+                    log::info!(
+                        "LUT {:?}: {}x{}x{}, values in [{}, {}], {} NaN or infinite",
+                        desc.name,
+                        desc.width,
+                        desc.height,
+                        desc.depth,
+                        stats.min,
+                        stats.max,
+                        stats.non_finite_count
+                    );
+                }
+                NeedsRedraw::Yes
+            }
+            Action::ToggleDiffBaseline => {
+                self.diff_baseline = if self.diff_baseline == Some(self.current_shader) {
+                    log::info!("Cleared the diff baseline");
+                    None
+                } else {
+                    log::info!("Diff baseline: {:?}", self.current_shader());
+                    Some(self.current_shader)
+                };
+                self.last_logged_diff = None;
+                NeedsRedraw::Yes
+            }
+            Action::CycleDiffView => {
+                self.diff_view = match self.diff_view {
+                    None => Some(DiffMetric::Ciede2000),
+                    Some(DiffMetric::Ciede2000) => Some(DiffMetric::Itp),
+                    Some(DiffMetric::Itp) => None,
+                };
+
+                if let Some(metric) = self.diff_view {
+                    if self.diff_baseline.is_none() {
+                        log::warn!("No diff baseline; press B to use the current technique");
+                    }
+                    log::info!("Diff view: {}", metric.name());
+                }
+                self.last_logged_diff = None;
+                NeedsRedraw::Yes
+            }
+            Action::CycleDebugView => {
+                self.debug_view = self.debug_view.next();
+                NeedsRedraw::Yes
+            }
+            Action::CycleDisplayPrimaries => {
+                self.display_primaries = self.display_primaries.next();
+                log::info!("Display primaries: {}", self.display_primaries);
+                NeedsRedraw::Yes
+            }
+            Action::Screenshot => {
+                let output_target = self.viewer_output_target();
+                // The viewer is SDR, and JPEGs carry an ICC profile of the display primaries.
+                let format = CaptureFormat::Jpeg;
+
                 self.pending_image_capture = vec![PendingImageCapture {
                     ev: self.ev,
-                    file_path: "screenshot.jpg".into(),
+                    debug_view: self.debug_view,
+                    file_path: self.next_screenshot_path(format),
                     image_index: self.current_image,
                     shader_index: self.current_shader,
+                    output_target,
+                    adaptation: self
+                        .adaptation
+                        .adapted_log2_luminance()
+                        .filter(|_| self.adaptation_enabled)
+                        .map_or(CaptureAdaptation::Full, CaptureAdaptation::To),
                 }];
                 NeedsRedraw::Yes
             }
-            _ => NeedsRedraw::No,
+            Action::ScreenshotWindow => {
+                self.pending_window_capture = Some(self.next_screenshot_path(CaptureFormat::Jpeg));
+                NeedsRedraw::Yes
+            }
+            Action::EvUp => self.step_ev(1.0),
+            Action::EvDown => self.step_ev(-1.0),
+            Action::ResetEv => {
+                self.ev = 0.0;
+                NeedsRedraw::Yes
+            }
+            Action::StoreBookmark(key) => {
+                self.store_bookmark(key);
+                NeedsRedraw::No
+            }
+            Action::RecallBookmark(key) => self.recall_bookmark(key),
         }
     }
 
+    /// Moves the EV to the next multiple of the EV step in `direction`.
+    fn step_ev(&mut self, direction: f64) -> NeedsRedraw {
+        let step = self.key_bindings.ev_step;
+        // So that values just off the grid don't skip a step.
+        let steps = self.ev / step;
+        let steps = if direction > 0.0 {
+            (steps + 1e-6).floor() + 1.0
+        } else {
+            (steps - 1e-6).ceil() - 1.0
+        };
+        self.ev = steps * step;
+        NeedsRedraw::Yes
+    }
+
     pub fn handle_window_event(&mut self, event: WindowEvent) -> NeedsRedraw {
         match event {
             WindowEvent::KeyboardInput { input, .. } => self.handle_keyboard_input(input),
@@ -301,13 +1369,29 @@ impl AppState {
                 let mut needs_redraw = NeedsRedraw::No;
 
                 if self.interaction.dragging_ev {
-                    self.ev += (self.interaction.last_cursor_position[1] - position.y) / 100.0;
+                    self.ev += (self.interaction.last_cursor_position[1] - position.y)
+                        * self.key_bindings.ev_drag_sensitivity;
                     needs_redraw = NeedsRedraw::Yes;
                 }
 
                 self.interaction.last_cursor_position = [position.x, position.y];
                 needs_redraw
             }
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(_, lines) if lines != 0.0 => {
+                    self.step_ev(lines.signum() as f64)
+                }
+                // Touchpads scroll by pixels; treated like dragging.
+                MouseScrollDelta::PixelDelta(position) => {
+                    self.ev += position.y * self.key_bindings.ev_drag_sensitivity;
+                    NeedsRedraw::Yes
+                }
+                _ => NeedsRedraw::No,
+            },
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.interaction.modifiers = modifiers;
+                NeedsRedraw::No
+            }
             WindowEvent::MouseInput { state, button, .. } => {
                 if matches!(button, MouseButton::Left) {
                     self.interaction.dragging_ev = matches!(state, ElementState::Pressed);
@@ -319,6 +1403,161 @@ impl AppState {
         }
     }
 
+    pub fn restore_session(&mut self, session: &Session) {
+        if let Some(view) = &session.view {
+            if let Some((image_index, shader_index)) = self.resolve_view(view) {
+                self.show_view(view, image_index, shader_index);
+            }
+        }
+
+        self.image_ev = session
+            .image_ev
+            .iter()
+            .filter_map(|(image, ev)| Some((self.image_pool.find_image(image)?, *ev)))
+            .collect();
+        self.auto_exposure = session.auto_exposure;
+        if let Some(adaptation) = session.adaptation {
+            self.adaptation_enabled = adaptation;
+        }
+        if let Some(display_primaries) = session.display_primaries {
+            self.display_primaries = display_primaries;
+        }
+        self.overlays = session.overlays;
+        self.diff_baseline = session
+            .diff_baseline
+            .as_ref()
+            .and_then(|name| self.shaders.iter().position(|s| s.name() == *name));
+        self.diff_view = session.diff_view;
+        self.bookmarks = session.bookmarks.clone();
+    }
+
+    pub fn session(&self) -> Session {
+        let mut image_ev = self.image_ev.clone();
+        image_ev.insert(self.current_image, self.ev);
+
+        let mut image_ev: Vec<(PathBuf, f64)> = image_ev
+            .into_iter()
+            .filter_map(|(image_index, ev)| {
+                Some((self.image_pool.get_image_path(image_index)?.clone(), ev))
+            })
+            .collect();
+        image_ev.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Session {
+            view: self.current_view(),
+            image_ev,
+            auto_exposure: self.auto_exposure,
+            adaptation: Some(self.adaptation_enabled),
+            display_primaries: Some(self.display_primaries),
+            overlays: OverlaySettings {
+                // LUTs are identified by index, which isn't stable across runs.
+                lut: None,
+                ..self.overlays
+            },
+            diff_baseline: self.diff_baseline.map(|index| self.shaders[index].name()),
+            diff_view: self.diff_view,
+            bookmarks: self.bookmarks.clone(),
+        }
+    }
+
+    /// Frees the render targets kept between frames; they'd otherwise be reported as leaked.
+    pub fn destroy_render_targets(&self, gl: &gl::Gl) {
+        for technique in &self.shaders {
+            technique.passes.destroy_targets(gl);
+        }
+    }
+
+    /// Writes the session to `session_file`, if any.
+    pub fn save_session(&self) {
+        if let Some(session_file) = &self.session_file {
+            match self.session().save(session_file) {
+                Ok(()) => log::info!("Saved the session to {:?}", session_file),
+                Err(err) => log::error!("Failed to save the session: {:?}", err),
+            }
+        }
+    }
+
+    fn current_view(&self) -> Option<View> {
+        Some(View {
+            image: self.image_pool.get_image_path(self.current_image)?.clone(),
+            technique: self.current_shader(),
+            ev: self.ev,
+            debug_view: self.debug_view,
+        })
+    }
+
+    /// Indices of the image and technique of `view`, or `None` if either no longer exists.
+    fn resolve_view(&self, view: &View) -> Option<(usize, usize)> {
+        let image_index = self.image_pool.find_image(&view.image);
+        let shader_index = self
+            .shaders
+            .iter()
+            .position(|shader| shader.name() == view.technique);
+
+        if image_index.is_none() {
+            log::warn!("Image {:?} not found", view.image);
+        }
+        if shader_index.is_none() {
+            log::warn!("Technique {:?} not found", view.technique);
+        }
+
+        image_index.zip(shader_index)
+    }
+
+    fn show_view(&mut self, view: &View, image_index: usize, shader_index: usize) {
+        self.switch_image(image_index);
+        self.current_shader = shader_index;
+        self.ev = view.ev;
+        self.debug_view = view.debug_view;
+        // The view's EV takes precedence.
+        self.needs_auto_exposure = false;
+    }
+
+    fn store_bookmark(&mut self, key: u8) {
+        let view = match self.current_view() {
+            Some(view) => view,
+            None => return,
+        };
+
+        let image_name = view
+            .image
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let bookmark = Bookmark {
+            key,
+            name: format!("{} EV {:+.2} {}", image_name, view.ev, view.technique),
+            view,
+        };
+        log::info!("Bookmark {}: {}", key, bookmark.name);
+
+        self.bookmarks.retain(|b| b.key != key);
+        self.bookmarks.push(bookmark);
+        self.bookmarks.sort_by_key(|b| b.key);
+
+        // Right away, rather than only on exit, so that it survives a crash.
+        self.save_session();
+    }
+
+    fn recall_bookmark(&mut self, key: u8) -> NeedsRedraw {
+        let bookmark = match self.bookmarks.iter().find(|b| b.key == key) {
+            Some(bookmark) => bookmark.clone(),
+            None => {
+                log::info!("No bookmark {}; press Ctrl+{} to store one", key, key);
+                return NeedsRedraw::No;
+            }
+        };
+
+        match self.resolve_view(&bookmark.view) {
+            Some((image_index, shader_index)) => {
+                log::info!("Bookmark {}: {}", key, bookmark.name);
+                self.show_view(&bookmark.view, image_index, shader_index);
+                NeedsRedraw::Yes
+            }
+            None => NeedsRedraw::No,
+        }
+    }
+
     pub fn current_shader(&self) -> String {
         self.shaders[self.current_shader].name()
     }
@@ -329,13 +1568,22 @@ impl AppState {
             .and_then(|path| Some(path.file_name()?.to_string_lossy().as_ref().to_owned()))
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn request_batch(
         &mut self,
         ev_min: f64,
         ev_max: f64,
         ev_step: f64,
         shader_name: &str,
+        output_target: OutputTarget,
+        format: CaptureFormat,
+        adaptation_frame_rate: Option<f64>,
     ) -> anyhow::Result<()> {
+        format.ensure_supports(output_target)?;
+        if let Some(frame_rate) = adaptation_frame_rate {
+            anyhow::ensure!(frame_rate > 0.0, "The frame rate must be positive");
+        }
+
         let root_dir = &PathBuf::from("batch");
         let shader_index = self
             .shaders
@@ -360,11 +1608,28 @@ impl AppState {
 
                                 PendingImageCapture {
                                     ev,
-                                    file_path: root_dir
-                                        .join(image_path.file_name().unwrap())
-                                        .join(format!("{:03} - EV {}.jpg", step_index, ev)),
+                                    debug_view: DebugView::None,
+                                    file_path: root_dir.join(image_path.file_name().unwrap()).join(
+                                        format!(
+                                            "{:03} - EV {}.{}",
+                                            step_index,
+                                            ev,
+                                            format.extension()
+                                        ),
+                                    ),
                                     image_index,
                                     shader_index,
+                                    output_target,
+                                    // Each image's EV sweep is a sequence.
+                                    adaptation: match adaptation_frame_rate {
+                                        None => CaptureAdaptation::Full,
+                                        Some(frame_rate) if step_index == 0 => {
+                                            CaptureAdaptation::SequenceStart(frame_rate)
+                                        }
+                                        Some(frame_rate) => {
+                                            CaptureAdaptation::SequenceFrame(frame_rate)
+                                        }
+                                    },
                                 }
                             })
                         })
@@ -376,18 +1641,249 @@ impl AppState {
 
         Ok(())
     }
+
+    pub fn request_lut_dump(&mut self, dir: PathBuf, format: LutDumpFormat) {
+        self.pending_lut_dump = Some(PendingLutDump { dir, format });
+    }
+
+    pub fn request_lut_bake(
+        &mut self,
+        desc: LutBakeDesc,
+        shader_name: &str,
+        file_path: PathBuf,
+    ) -> anyhow::Result<()> {
+        let shader_index = self
+            .shaders
+            .iter()
+            .position(|shader| shader.name() == shader_name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown shader {:?}", shader_name))?;
+
+        anyhow::ensure!(
+            desc.size >= 2,
+            "The LUT must have at least 2 points per axis"
+        );
+        // The lattice is rendered as a `size` by `size * size` image.
+        anyhow::ensure!(
+            desc.size * desc.size <= self.max_texture_size,
+            "A LUT of {} points per axis is too large to bake; the GPU supports at most {}",
+            desc.size,
+            (self.max_texture_size as f64).sqrt() as usize
+        );
+
+        // Fail early rather than after baking.
+        LutFileFormat::from_path(&file_path)?;
+
+        if !self.shaders[shader_index].passes.is_empty() {
+            log::warn!(
+                "{:?} has spatial passes, which a LUT can't capture",
+                shader_name
+            );
+        }
+
+        self.pending_lut_bake.push(PendingLutBake {
+            desc,
+            file_path,
+            shader_index,
+        });
+
+        Ok(())
+    }
+
+    /// Captures every bookmark into `dir`, named after it.
+    pub fn request_bookmark_captures(
+        &mut self,
+        bookmarks: &[Bookmark],
+        dir: &Path,
+        format: CaptureFormat,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(!bookmarks.is_empty(), "The session has no bookmarks");
+
+        for bookmark in bookmarks {
+            let (image_index, shader_index) = match self.resolve_view(&bookmark.view) {
+                Some(indices) => indices,
+                None => continue,
+            };
+
+            self.pending_image_capture.push(PendingImageCapture {
+                ev: bookmark.view.ev,
+                debug_view: bookmark.view.debug_view,
+                file_path: dir.join(format!("{}.{}", bookmark.file_stem(), format.extension())),
+                image_index,
+                shader_index,
+                output_target: self.viewer_output_target(),
+                adaptation: CaptureAdaptation::Full,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Measures the hue drift of the named techniques (or all of them, if `shader_names` is empty),
+    /// writing a report for each into `dir`.
+    pub fn request_analysis(&mut self, shader_names: &[String], dir: &Path) -> anyhow::Result<()> {
+        let shader_indices: Vec<usize> = if shader_names.is_empty() {
+            (0..self.shaders.len()).collect()
+        } else {
+            shader_names
+                .iter()
+                .map(|shader_name| {
+                    self.shaders
+                        .iter()
+                        .position(|shader| shader.name() == *shader_name)
+                        .ok_or_else(|| anyhow::anyhow!("Unknown shader {:?}", shader_name))
+                })
+                .collect::<anyhow::Result<_>>()?
+        };
+
+        self.pending_analysis
+            .extend(
+                shader_indices
+                    .into_iter()
+                    .map(|shader_index| PendingAnalysis {
+                        dir: dir.to_owned(),
+                        shader_index,
+                    }),
+            );
+
+        Ok(())
+    }
+
+    /// Compares the output of a technique against either another technique or a reference
+    /// image, for every input image, writing ΔE heatmaps and stats into `dir`.
+    pub fn request_diff(
+        &mut self,
+        shader_name: &str,
+        baseline_shader_name: Option<&str>,
+        reference: Option<PathBuf>,
+        ev: f64,
+        dir: PathBuf,
+    ) -> anyhow::Result<()> {
+        let find_shader = |shader_name: &str| {
+            self.shaders
+                .iter()
+                .position(|shader| shader.name() == shader_name)
+                .ok_or_else(|| anyhow::anyhow!("Unknown shader {:?}", shader_name))
+        };
+
+        let shader_index = find_shader(shader_name)?;
+        let baseline = match (baseline_shader_name, reference) {
+            (Some(baseline_shader_name), None) => {
+                DiffBaseline::Technique(find_shader(baseline_shader_name)?)
+            }
+            (None, Some(reference)) => DiffBaseline::Reference(reference),
+            _ => anyhow::bail!("Expected either a baseline shader or a reference image"),
+        };
+
+        for image_index in 0..self.image_pool.image_count() {
+            self.pending_diff.push(PendingDiff {
+                baseline: baseline.clone(),
+                dir: dir.clone(),
+                ev,
+                image_index,
+                shader_index,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Bakes the named techniques (or all of them, if `shader_names` is empty) into `dir`,
+    /// along with an OCIO config which references them.
+    pub fn request_ocio_export(
+        &mut self,
+        shader_names: &[String],
+        dir: &Path,
+        size: usize,
+        log2_min: f64,
+        log2_max: f64,
+    ) -> anyhow::Result<()> {
+        let shader_names: Vec<String> = if shader_names.is_empty() {
+            self.shaders.iter().map(Technique::name).collect()
+        } else {
+            shader_names.to_vec()
+        };
+
+        let desc = LutBakeDesc {
+            size,
+            domain: LutDomain::Log2 {
+                min: log2_min,
+                max: log2_max,
+            },
+            ev: 0.0,
+        };
+
+        for shader_name in &shader_names {
+            self.request_lut_bake(
+                desc,
+                shader_name,
+                dir.join(OCIO_LUT_FOLDER)
+                    .join(ocio_lut_file_name(shader_name)),
+            )?;
+        }
+
+        write_ocio_config(dir, &shader_names, log2_min, log2_max)
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_texture(
     gl: &gl::Gl,
     texture: &Texture,
+    shader_lib: &ShaderLib,
     shader_program: u32,
-    size: [usize; 2],
-    ev: f64,
+    frame: FrameConstants,
+    debug_view: DebugView,
     lut_lib: &LutLib,
+    image_stats: &ImageStats,
+    technique: &Technique,
+    output_target: OutputTarget,
 ) {
+    // Images are measured either way, for the adaptation below. Synthetic inputs are drawn
+    // every frame by some overlays, so only when the technique reads the statistics.
+    let image_index = usize::try_from(frame.image_index).ok();
+    let stats =
+        if image_index.is_some() || technique.uses_image_stats(gl, shader_lib, shader_program) {
+            image_stats.statistics(gl, shader_lib, texture, image_index)
+        } else {
+            None
+        };
+    image_stats.bind(gl, stats.as_ref());
+
+    let frame = FrameConstants {
+        display_peak_nits: output_target.encoding.peak_nits(),
+        adapted_log2_luminance: if frame.adapted_log2_luminance.is_nan() {
+            stats.map_or(0.0, |stats| stats.log2_average() + frame.ev as f64) as f32
+        } else {
+            frame.adapted_log2_luminance
+        },
+        ..frame
+    };
+
+    // The passes bind their own framebuffers, and unbind them when evicting old targets.
+    let (mut draw_fbo, mut read_fbo) = (0, 0);
     unsafe {
-        gl.Viewport(0, 0, size[0] as _, size[1] as _);
+        gl.GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut draw_fbo);
+        gl.GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut read_fbo);
+    }
+
+    technique
+        .passes
+        .run(gl, shader_lib, lut_lib, texture, frame);
+    unsafe {
+        gl.BindFramebuffer(gl::DRAW_FRAMEBUFFER, draw_fbo as u32);
+        gl.BindFramebuffer(gl::READ_FRAMEBUFFER, read_fbo as u32);
+    }
+
+    shader_lib.bind_frame_constants(
+        gl,
+        &FrameConstants {
+            resolution: [texture.size[0] as f32, texture.size[1] as f32],
+            ..frame
+        },
+    );
+
+    unsafe {
+        gl.Viewport(0, 0, texture.size[0] as _, texture.size[1] as _);
 
         gl.UseProgram(shader_program);
 
@@ -396,8 +1892,7 @@ fn draw_texture(
         {
             gl.ActiveTexture(gl::TEXTURE0);
             gl.BindTexture(gl::TEXTURE_2D, texture.id);
-            let loc =
-                gl.GetUniformLocation(shader_program, "input_texture\0".as_ptr() as *const i8);
+            let loc = shader_lib.uniform_location(gl, shader_program, "input_texture");
             if loc != -1 {
                 gl.Uniform1i(loc, tex_unit);
                 tex_unit += 1;
@@ -405,17 +1900,25 @@ fn draw_texture(
         }
 
         for (lut_desc, lut_texture) in lut_lib.iter() {
-            let uniform_name = CString::new(lut_desc.name.clone()).unwrap();
-            let loc = gl.GetUniformLocation(shader_program, uniform_name.as_ptr() as *const i8);
-            if loc != -1 {
-                // log::info!("Binding lut {:?}", lut_desc.name);
-                gl.ActiveTexture(gl::TEXTURE0 + tex_unit as gl::types::GLenum);
-                gl.BindTexture(lut_texture.ty, lut_texture.id);
-                gl.Uniform1i(loc, tex_unit);
-                tex_unit += 1;
-            }
+            shader_lib.bind_sampler(
+                gl,
+                shader_program,
+                &lut_desc.name,
+                lut_texture,
+                &mut tex_unit,
+            );
+        }
+
+        technique
+            .passes
+            .bind_outputs(gl, shader_lib, shader_program, &mut tex_unit);
+
+        if let Some(external_lut) = &technique.external_lut {
+            external_lut.bind(gl, shader_lib, shader_program, &mut tex_unit);
         }
 
+        output_target.bind(gl, shader_lib, shader_program);
+
         /*for (lut_desc, lut_texture) in lut_lib.iter() {
             let uniform_name = CString::new(lut_desc.name.clone()).unwrap();
             let loc = gl.GetUniformLocation(shader_program, uniform_name.as_ptr() as *const i8);
@@ -435,9 +1938,16 @@ fn draw_texture(
         }*/
 
         {
-            let loc = gl.GetUniformLocation(shader_program, "input_ev\0".as_ptr() as *const i8);
+            let loc = shader_lib.uniform_location(gl, shader_program, "input_ev");
+            if loc != -1 {
+                gl.Uniform1f(loc, frame.ev);
+            }
+        }
+
+        {
+            let loc = shader_lib.uniform_location(gl, shader_program, "debug_view");
             if loc != -1 {
-                gl.Uniform1f(loc, ev as f32);
+                gl.Uniform1i(loc, debug_view as i32);
             }
         }
 
@@ -0,0 +1,68 @@
+use std::ffi::c_void;
+
+#[derive(Clone, Copy)]
+pub struct Buffer {
+    pub id: u32,
+    pub size: usize,
+}
+
+impl Buffer {
+    pub fn new(gl: &gl::Gl, size: usize) -> Self {
+        unsafe {
+            let mut id = 0;
+            gl.CreateBuffers(1, &mut id);
+            gl.NamedBufferStorage(id, size as _, std::ptr::null(), gl::DYNAMIC_STORAGE_BIT);
+
+            Self { id, size }
+        }
+    }
+
+    /// Fills the buffer with zeros.
+    pub fn clear(&self, gl: &gl::Gl) {
+        unsafe {
+            gl.ClearNamedBufferData(
+                self.id,
+                gl::R32UI,
+                gl::RED_INTEGER,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+    }
+
+    pub fn bind_storage(&self, gl: &gl::Gl, binding: u32) {
+        unsafe {
+            gl.BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, self.id);
+        }
+    }
+
+    pub fn bind_uniform(&self, gl: &gl::Gl, binding: u32) {
+        unsafe {
+            gl.BindBufferBase(gl::UNIFORM_BUFFER, binding, self.id);
+        }
+    }
+
+    /// Uploads `data` to the start of the buffer.
+    pub fn write<T: Copy>(&self, gl: &gl::Gl, data: &[T]) {
+        let size = std::mem::size_of_val(data);
+        assert!(size <= self.size);
+
+        unsafe {
+            gl.NamedBufferSubData(self.id, 0, size as _, data.as_ptr() as *const c_void);
+        }
+    }
+
+    /// Reads back the whole buffer, stalling until the GPU is done writing to it.
+    pub fn read<T: Copy + Default>(&self, gl: &gl::Gl) -> Vec<T> {
+        let mut data = vec![T::default(); self.size / std::mem::size_of::<T>()];
+        unsafe {
+            gl.GetNamedBufferSubData(
+                self.id,
+                0,
+                (data.len() * std::mem::size_of::<T>()) as _,
+                data.as_mut_ptr() as *mut c_void,
+            );
+        }
+        data
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{
+    buffer::Buffer,
+    overlay::{draw_overlay, OverlayRect},
+    shader::{dispatch_compute, ShaderKey},
+    shader_lib::ShaderLib,
+    texture::Texture,
+};
+
+// Must match `CHROMATICITY_RESOLUTION` in `shaders/scope/chromaticity_common.glsl`
+const CHROMATICITY_RESOLUTION: usize = 256;
+
+pub const CHROMATICITY_OVERLAY_SIZE: [i32; 2] = [360, 360];
+
+/// CIE 1931 xy plot of the input stimulus and display output chromaticities,
+/// with the spectral locus, sRGB and BT.2020 gamuts, and the D65 white point.
+pub struct ChromaticityDiagram {
+    compute_shader: ShaderKey,
+    draw_shader: ShaderKey,
+    bins_buffer: Buffer,
+    pixel_count: f32,
+}
+
+impl ChromaticityDiagram {
+    pub fn new(shader_lib: &mut ShaderLib, gl: &gl::Gl) -> Self {
+        let compute_shader = shader_lib.add_compute_shader("shaders/scope/chromaticity.glsl");
+        let draw_shader = shader_lib.add_shader("shaders/scope/chromaticity_draw.glsl");
+
+        // Input and output
+        let bins_buffer = Buffer::new(
+            gl,
+            2 * CHROMATICITY_RESOLUTION * CHROMATICITY_RESOLUTION * std::mem::size_of::<u32>(),
+        );
+
+        Self {
+            compute_shader,
+            draw_shader,
+            bins_buffer,
+            pixel_count: 1.0,
+        }
+    }
+
+    /// Both textures must be the same size.
+    pub fn compute(
+        &mut self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        input: &Texture,
+        output: &Texture,
+    ) {
+        let shader_program = match shader_lib.get_shader_gl_handle(&self.compute_shader) {
+            Some(shader_program) => shader_program,
+            None => return,
+        };
+
+        self.bins_buffer.clear(gl);
+        self.pixel_count = (input.size[0] * input.size[1]) as f32;
+
+        unsafe {
+            gl.UseProgram(shader_program);
+
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.BindTexture(gl::TEXTURE_2D, input.id);
+            let loc = shader_lib.uniform_location(gl, shader_program, "input_texture");
+            gl.Uniform1i(loc, 0);
+
+            gl.ActiveTexture(gl::TEXTURE1);
+            gl.BindTexture(gl::TEXTURE_2D, output.id);
+            let loc = shader_lib.uniform_location(gl, shader_program, "output_texture");
+            gl.Uniform1i(loc, 1);
+
+            self.bins_buffer.bind_storage(gl, 0);
+
+            dispatch_compute(
+                gl,
+                shader_program,
+                [input.size[0] as u32, input.size[1] as u32, 1],
+            );
+
+            gl.UseProgram(0);
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+        }
+    }
+
+    pub fn draw(&self, gl: &gl::Gl, shader_lib: &ShaderLib, rect: OverlayRect) {
+        if let Some(shader_program) = shader_lib.get_shader_gl_handle(&self.draw_shader) {
+            draw_overlay(gl, shader_program, rect, |shader_program| unsafe {
+                self.bins_buffer.bind_storage(gl, 0);
+
+                let loc = shader_lib.uniform_location(gl, shader_program, "pixel_count");
+                gl.Uniform1f(loc, self.pixel_count);
+            });
+        }
+    }
+}
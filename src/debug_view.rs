@@ -0,0 +1,32 @@
+// Must match the `DEBUG_VIEW_*` defines in `shaders/inc/debug_view.glsl`
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebugView {
+    #[default]
+    None = 0,
+    /// Exposure of the input stimulus in stops relative to mid-gray.
+    FalseColor = 1,
+    /// Pixels where the technique output is outside of the 0..1 range on any channel.
+    ClippedOutput = 2,
+    /// Pixels where the input stimulus is outside of the output gamut.
+    OutOfGamutInput = 3,
+}
+
+impl DebugView {
+    pub fn next(self) -> Self {
+        match self {
+            Self::None => Self::FalseColor,
+            Self::FalseColor => Self::ClippedOutput,
+            Self::ClippedOutput => Self::OutOfGamutInput,
+            Self::OutOfGamutInput => Self::None,
+        }
+    }
+
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::FalseColor => Some("false color"),
+            Self::ClippedOutput => Some("clipped output"),
+            Self::OutOfGamutInput => Some("out-of-gamut input"),
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    lut_bake::{LutDomain, DOMAIN_COMMENT_PREFIX},
+    shader_lib::ShaderLib,
+    texture::Texture,
+};
+
+/// Contents of a Resolve/Adobe `.cube` file. When both a 1D and a 3D table are present,
+/// the 1D one is applied first, as a shaper.
+pub struct CubeFile {
+    pub domain: Option<LutDomain>,
+    /// Input `[min, max]` of the 1D table, from `DOMAIN_MIN`/`MAX` or `LUT_1D_INPUT_RANGE`.
+    pub input_range_1d: [[f32; 3]; 2],
+    /// Input `[min, max]` of the 3D table, from `DOMAIN_MIN`/`MAX` or `LUT_3D_INPUT_RANGE`;
+    /// after the shaper, if any.
+    pub input_range_3d: [[f32; 3]; 2],
+    pub table_1d: Vec<[f32; 3]>,
+    pub size_3d: usize,
+    pub table_3d: Vec<[f32; 3]>,
+}
+
+impl CubeFile {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Reading {:?}", path))?;
+        Self::parse(&text).with_context(|| format!("Parsing {:?}", path))
+    }
+
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        fn parse_floats<const N: usize>(words: &[&str]) -> anyhow::Result<[f32; N]> {
+            anyhow::ensure!(words.len() == N, "Expected {} values, got {:?}", N, words);
+            let mut res = [0.0; N];
+            for (dst, word) in res.iter_mut().zip(words) {
+                *dst = word
+                    .parse()
+                    .with_context(|| format!("Invalid number {:?}", word))?;
+            }
+            Ok(res)
+        }
+
+        let mut domain = None;
+        let mut input_range_1d = [[0.0; 3], [1.0; 3]];
+        let mut input_range_3d = [[0.0; 3], [1.0; 3]];
+        let mut size_1d = 0;
+        let mut size_3d = 0;
+        let mut values = Vec::new();
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(spec) = comment.trim().strip_prefix(DOMAIN_COMMENT_PREFIX) {
+                    domain = Some(spec.parse()?);
+                }
+                continue;
+            }
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let result =
+                match words.as_slice() {
+                    [] => Ok(()),
+                    ["TITLE", ..] => Ok(()),
+                    ["LUT_1D_SIZE", size] => {
+                        size.parse().map(|size| size_1d = size).map_err(Into::into)
+                    }
+                    ["LUT_3D_SIZE", size] => {
+                        size.parse().map(|size| size_3d = size).map_err(Into::into)
+                    }
+                    // Adobe's files have a single table, which these apply to.
+                    ["DOMAIN_MIN", rest @ ..] => parse_floats(rest).map(|v| {
+                        input_range_1d[0] = v;
+                        input_range_3d[0] = v;
+                    }),
+                    ["DOMAIN_MAX", rest @ ..] => parse_floats(rest).map(|v| {
+                        input_range_1d[1] = v;
+                        input_range_3d[1] = v;
+                    }),
+                    // Resolve's scalar variants, per table.
+                    ["LUT_1D_INPUT_RANGE", rest @ ..] => parse_floats::<2>(rest)
+                        .map(|[min, max]| input_range_1d = [[min; 3], [max; 3]]),
+                    ["LUT_3D_INPUT_RANGE", rest @ ..] => parse_floats::<2>(rest)
+                        .map(|[min, max]| input_range_3d = [[min; 3], [max; 3]]),
+                    [first, ..] if first.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                        log::warn!("Ignoring unknown .cube keyword {:?}", first);
+                        Ok(())
+                    }
+                    rest => parse_floats(rest).map(|v| values.push(v)),
+                };
+
+            result.with_context(|| format!("Line {}", line_index + 1))?;
+        }
+
+        anyhow::ensure!(
+            size_1d > 0 || size_3d > 0,
+            "Missing LUT_1D_SIZE or LUT_3D_SIZE"
+        );
+
+        let expected_count = size_1d + size_3d * size_3d * size_3d;
+        anyhow::ensure!(
+            values.len() == expected_count,
+            "Expected {} entries, found {}",
+            expected_count,
+            values.len()
+        );
+
+        let table_3d = values.split_off(size_1d);
+
+        Ok(Self {
+            domain,
+            input_range_1d,
+            input_range_3d,
+            table_1d: values,
+            size_3d,
+            table_3d,
+        })
+    }
+}
+
+/// A `.cube` file uploaded to the GPU, applied to the stimulus by `shaders/external_lut/apply.glsl`.
+/// The LUT output is assumed to be sRGB-encoded.
+pub struct ExternalLut {
+    pub name: String,
+    pub path: PathBuf,
+    domain: LutDomain,
+    input_range_1d: [[f32; 3]; 2],
+    input_range_3d: [[f32; 3]; 2],
+    // Both samplers need a texture of their type on a unit of their own, even when the file
+    // lacks the table; a single texel stands in for it.
+    texture_1d: Texture,
+    texture_3d: Texture,
+    has_1d: bool,
+    has_3d: bool,
+}
+
+impl ExternalLut {
+    /// `default_domain` is used unless the file records its own.
+    pub fn load(gl: &gl::Gl, path: &Path, default_domain: LutDomain) -> anyhow::Result<Self> {
+        let cube = CubeFile::load(path)?;
+
+        let name = path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        Ok(Self {
+            name,
+            path: path.to_owned(),
+            domain: cube.domain.unwrap_or(default_domain),
+            input_range_1d: cube.input_range_1d,
+            input_range_3d: cube.input_range_3d,
+            has_1d: !cube.table_1d.is_empty(),
+            has_3d: cube.size_3d > 0,
+            texture_1d: if cube.table_1d.is_empty() {
+                Texture::new_1d_rgb32f(gl, &[[0.0; 3]])
+            } else {
+                Texture::new_1d_rgb32f(gl, &cube.table_1d)
+            },
+            texture_3d: if cube.size_3d > 0 {
+                Texture::new_3d_rgb32f(gl, cube.size_3d, &cube.table_3d)
+            } else {
+                Texture::new_3d_rgb32f(gl, 1, &[[0.0; 3]])
+            },
+        })
+    }
+
+    /// Binds the LUT textures and uniforms to `shader_program`, starting at texture unit `tex_unit`.
+    pub fn bind(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        shader_program: u32,
+        tex_unit: &mut i32,
+    ) {
+        unsafe {
+            let uniform_location =
+                |name: &str| shader_lib.uniform_location(gl, shader_program, name);
+
+            for (texture, has_table, sampler_name, enabled_name, size_name) in [
+                (
+                    self.texture_1d,
+                    self.has_1d,
+                    "external_lut_1d",
+                    "external_lut_has_1d",
+                    "external_lut_size_1d",
+                ),
+                (
+                    self.texture_3d,
+                    self.has_3d,
+                    "external_lut_3d",
+                    "external_lut_has_3d",
+                    "external_lut_size_3d",
+                ),
+            ] {
+                gl.ActiveTexture(gl::TEXTURE0 + *tex_unit as gl::types::GLenum);
+                gl.BindTexture(texture.ty, texture.id);
+                gl.Uniform1i(uniform_location(sampler_name), *tex_unit);
+                *tex_unit += 1;
+
+                gl.Uniform1i(uniform_location(enabled_name), has_table as i32);
+                gl.Uniform1i(uniform_location(size_name), texture.size[0] as i32);
+            }
+
+            // Must match the `EXTERNAL_LUT_DOMAIN_*` defines in `shaders/external_lut/apply.glsl`
+            let (domain, log2_range) = match self.domain {
+                LutDomain::Linear => (0, [0.0, 1.0]),
+                LutDomain::Log2 { min, max } => (1, [min as f32, max as f32]),
+                LutDomain::AcesCct => (2, [0.0, 1.0]),
+            };
+            gl.Uniform1i(uniform_location("external_lut_domain"), domain);
+            gl.Uniform2f(
+                uniform_location("external_lut_log2_range"),
+                log2_range[0],
+                log2_range[1],
+            );
+
+            gl.Uniform3fv(
+                uniform_location("external_lut_input_range_1d"),
+                2,
+                self.input_range_1d.as_ptr() as *const f32,
+            );
+            gl.Uniform3fv(
+                uniform_location("external_lut_input_range_3d"),
+                2,
+                self.input_range_3d.as_ptr() as *const f32,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity_2x2x2() -> String {
+        let mut text = String::new();
+        for b in 0..2 {
+            for g in 0..2 {
+                for r in 0..2 {
+                    text += &format!("{} {} {}\n", r, g, b);
+                }
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn parses_3d_size_and_table() {
+        let lut = CubeFile::parse(&format!("LUT_3D_SIZE 2\n{}", identity_2x2x2())).unwrap();
+
+        assert_eq!(lut.size_3d, 2);
+        assert!(lut.table_1d.is_empty());
+        assert_eq!(lut.table_3d.len(), 8);
+        assert_eq!(lut.table_3d[1], [1.0, 0.0, 0.0]);
+        assert_eq!(lut.table_3d[7], [1.0, 1.0, 1.0]);
+        assert_eq!(lut.input_range_1d, [[0.0; 3], [1.0; 3]]);
+        assert_eq!(lut.input_range_3d, [[0.0; 3], [1.0; 3]]);
+        assert!(lut.domain.is_none());
+    }
+
+    #[test]
+    fn parses_domain_min_max() {
+        let lut = CubeFile::parse(&format!(
+            "DOMAIN_MIN -0.5 0 0.25\nDOMAIN_MAX 2 4 8\nLUT_3D_SIZE 2\n{}",
+            identity_2x2x2()
+        ))
+        .unwrap();
+
+        assert_eq!(lut.input_range_3d, [[-0.5, 0.0, 0.25], [2.0, 4.0, 8.0]]);
+
+        assert!(CubeFile::parse(&format!(
+            "DOMAIN_MIN 0 0\nLUT_3D_SIZE 2\n{}",
+            identity_2x2x2()
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn keeps_the_input_ranges_of_both_tables() {
+        let lut = CubeFile::parse(&format!(
+            "LUT_3D_INPUT_RANGE 0 1.5\nLUT_1D_SIZE 2\nLUT_1D_INPUT_RANGE -0.25 16\nLUT_3D_SIZE 2\n0 0 0\n1 1 1\n{}",
+            identity_2x2x2()
+        ))
+        .unwrap();
+
+        assert_eq!(lut.table_1d, vec![[0.0; 3], [1.0; 3]]);
+        assert_eq!(lut.table_3d.len(), 8);
+        assert_eq!(lut.input_range_1d, [[-0.25; 3], [16.0; 3]]);
+        assert_eq!(lut.input_range_3d, [[0.0; 3], [1.5; 3]]);
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let lut = CubeFile::parse(&format!(
+            "# Baked somewhere\nTITLE \"identity\"\n\n  \nLUT_3D_SIZE 2\n# {}log2:-10:6\n\n{}",
+            DOMAIN_COMMENT_PREFIX,
+            identity_2x2x2()
+        ))
+        .unwrap();
+
+        assert_eq!(lut.table_3d.len(), 8);
+        assert!(matches!(
+            lut.domain,
+            Some(LutDomain::Log2 { min, max }) if min == -10.0 && max == 6.0
+        ));
+    }
+
+    #[test]
+    fn rejects_mismatched_row_count() {
+        let rows = identity_2x2x2();
+        let missing_row = rows.lines().skip(1).collect::<Vec<_>>().join("\n");
+
+        assert!(CubeFile::parse(&format!("LUT_3D_SIZE 2\n{}", missing_row)).is_err());
+        assert!(CubeFile::parse(&format!("LUT_3D_SIZE 2\n{}0 0 0\n", rows)).is_err());
+        assert!(CubeFile::parse(&rows).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_floats() {
+        let rows = identity_2x2x2().replacen("1 0 0", "1 0,5 0", 1);
+        assert!(CubeFile::parse(&format!("LUT_3D_SIZE 2\n{}", rows)).is_err());
+
+        let rows = identity_2x2x2().replacen("1 0 0", "1 0", 1);
+        assert!(CubeFile::parse(&format!("LUT_3D_SIZE 2\n{}", rows)).is_err());
+
+        assert!(CubeFile::parse(&format!("LUT_3D_SIZE two\n{}", identity_2x2x2())).is_err());
+    }
+}
@@ -1,10 +1,26 @@
+use crate::texture::Texture;
+
 pub struct Fbo {
     fbo: u32,
-    rbo: u32,
+    pub texture: Texture,
 }
 
 impl Fbo {
     pub fn new(gl: &gl::Gl, size: [usize; 2]) -> Self {
+        Self::with_format(gl, size, gl::SRGB8_ALPHA8)
+    }
+
+    pub fn with_format(gl: &gl::Gl, size: [usize; 2], internal_format: gl::types::GLenum) -> Self {
+        Self::with_mip_levels(gl, size, internal_format, 1)
+    }
+
+    /// Renders into the top mip level.
+    pub fn with_mip_levels(
+        gl: &gl::Gl,
+        size: [usize; 2],
+        internal_format: gl::types::GLenum,
+        mip_levels: usize,
+    ) -> Self {
         unsafe {
             let mut fbo: u32 = 0;
             gl.GenFramebuffers(1, &mut fbo);
@@ -12,25 +28,20 @@ impl Fbo {
 
             gl.BindFramebuffer(gl::FRAMEBUFFER, fbo);
 
-            let mut rbo = 0;
-            gl.GenRenderbuffers(1, &mut rbo);
-            gl.BindRenderbuffer(gl::RENDERBUFFER, rbo);
-            gl.RenderbufferStorage(
-                gl::RENDERBUFFER,
-                gl::SRGB8_ALPHA8,
-                size[0] as _,
-                size[1] as _,
-            );
-            gl.FramebufferRenderbuffer(
+            // Backed by a texture rather than a renderbuffer, so that the output
+            // can be sampled by scopes and other analysis passes.
+            let texture = Texture::new_2d_empty_mipmapped(gl, size, internal_format, mip_levels);
+            gl.FramebufferTexture2D(
                 gl::FRAMEBUFFER,
                 gl::COLOR_ATTACHMENT0,
-                gl::RENDERBUFFER,
-                rbo,
+                texture.ty,
+                texture.id,
+                0,
             );
 
             gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
 
-            Self { fbo, rbo }
+            Self { fbo, texture }
         }
     }
 
@@ -63,11 +74,10 @@ impl Fbo {
 
         self.unbind(gl);
         self.unbind_read(gl);
+        self.texture.destroy(gl);
         unsafe {
-            gl.DeleteRenderbuffers(1, &self.rbo);
             gl.DeleteFramebuffers(1, &self.fbo);
         }
-        self.rbo = 0;
         self.fbo = 0;
     }
 }
@@ -0,0 +1,43 @@
+/// Number of `frame_params` values.
+pub const TECHNIQUE_PARAM_COUNT: usize = 8;
+
+// Uniform buffer binding of `FrameConstants` in `shaders/inc/frame_constants.glsl`
+pub const FRAME_CONSTANTS_BINDING: u32 = 0;
+
+/// Context which every technique can read, without any Rust changes.
+/// Offline rendering leaves out the time and cursor, so that it's deterministic.
+// Must match `FrameConstants` in `shaders/inc/frame_constants.glsl`, with std140 layout
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct FrameConstants {
+    /// Of the output, in pixels.
+    pub resolution: [f32; 2],
+    /// Over the image, in the same space as `input_uv`; negative when outside of it.
+    pub cursor_uv: [f32; 2],
+    /// Seconds since the start, as of the frame.
+    pub time: f32,
+    pub ev: f32,
+    pub display_peak_nits: f32,
+    /// -1 for synthetic inputs, such as LUT lattices and analysis grids.
+    pub image_index: i32,
+    pub params: [f32; TECHNIQUE_PARAM_COUNT],
+    /// Of the simulated eye, at the EV; NaN for fully adapted to the input.
+    pub adapted_log2_luminance: f32,
+    pub _padding: [f32; 3],
+}
+
+impl FrameConstants {
+    pub fn new(ev: f64, image_index: Option<usize>, params: [f32; TECHNIQUE_PARAM_COUNT]) -> Self {
+        Self {
+            resolution: [0.0; 2],
+            cursor_uv: [-1.0; 2],
+            time: 0.0,
+            ev: ev as f32,
+            display_peak_nits: 0.0,
+            image_index: image_index.map_or(-1, |i| i as i32),
+            params,
+            adapted_log2_luminance: f32::NAN,
+            _padding: [0.0; 3],
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{
+    buffer::Buffer,
+    overlay::{draw_overlay, OverlayRect},
+    shader::{dispatch_compute, ShaderKey},
+    shader_lib::ShaderLib,
+    texture::Texture,
+};
+
+// Must match `HISTOGRAM_BIN_COUNT` in `shaders/scope/histogram_common.glsl`
+pub const HISTOGRAM_BIN_COUNT: usize = 256;
+
+// R, G, B, and luminance, for both the input stimulus and the display output.
+const HISTOGRAM_CHANNEL_COUNT: usize = 8;
+
+pub const HISTOGRAM_OVERLAY_SIZE: [i32; 2] = [512, 320];
+
+/// Histograms of the input stimulus (log2 scale) and the display output (code values),
+/// accumulated on the GPU.
+pub struct Histogram {
+    compute_shader: ShaderKey,
+    draw_shader: ShaderKey,
+    bins_buffer: Buffer,
+}
+
+impl Histogram {
+    pub fn new(shader_lib: &mut ShaderLib, gl: &gl::Gl) -> Self {
+        let compute_shader = shader_lib.add_compute_shader("shaders/scope/histogram.glsl");
+        let draw_shader = shader_lib.add_shader("shaders/scope/histogram_draw.glsl");
+
+        let bins_buffer = Buffer::new(
+            gl,
+            HISTOGRAM_BIN_COUNT * HISTOGRAM_CHANNEL_COUNT * std::mem::size_of::<u32>(),
+        );
+
+        Self {
+            compute_shader,
+            draw_shader,
+            bins_buffer,
+        }
+    }
+
+    /// Bins the `input` stimulus at the given EV, and the technique's `output`.
+    /// Both textures must be the same size.
+    pub fn compute(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        input: &Texture,
+        ev: f64,
+        output: &Texture,
+    ) {
+        let shader_program = match shader_lib.get_shader_gl_handle(&self.compute_shader) {
+            Some(shader_program) => shader_program,
+            None => return,
+        };
+
+        self.bins_buffer.clear(gl);
+
+        unsafe {
+            gl.UseProgram(shader_program);
+
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.BindTexture(gl::TEXTURE_2D, input.id);
+            let loc = shader_lib.uniform_location(gl, shader_program, "input_texture");
+            gl.Uniform1i(loc, 0);
+
+            gl.ActiveTexture(gl::TEXTURE1);
+            gl.BindTexture(gl::TEXTURE_2D, output.id);
+            let loc = shader_lib.uniform_location(gl, shader_program, "output_texture");
+            gl.Uniform1i(loc, 1);
+
+            let loc = shader_lib.uniform_location(gl, shader_program, "input_ev");
+            gl.Uniform1f(loc, ev as f32);
+
+            self.bins_buffer.bind_storage(gl, 0);
+
+            dispatch_compute(
+                gl,
+                shader_program,
+                [input.size[0] as u32, input.size[1] as u32, 1],
+            );
+
+            gl.UseProgram(0);
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+        }
+    }
+
+    pub fn draw(&self, gl: &gl::Gl, shader_lib: &ShaderLib, rect: OverlayRect) {
+        if let Some(shader_program) = shader_lib.get_shader_gl_handle(&self.draw_shader) {
+            draw_overlay(gl, shader_program, rect, |_| {
+                self.bins_buffer.bind_storage(gl, 0);
+            });
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use anyhow::Context;
+use std::{fmt::Write as _, path::Path};
+
+use crate::{
+    buffer::Buffer,
+    fbo::Fbo,
+    shader::{dispatch_compute, ShaderKey},
+    shader_lib::ShaderLib,
+    texture::Texture,
+};
+
+// Must match the `HUE_ANALYSIS_*` defines in `shaders/analysis/hue_analysis_common.glsl`
+const HUE_ANALYSIS_HUE_COUNT: usize = 36;
+const HUE_ANALYSIS_EV_COUNT: usize = 25;
+const HUE_ANALYSIS_EV_MIN: f64 = -4.0;
+const HUE_ANALYSIS_EV_STEP: f64 = 0.5;
+const HUE_ANALYSIS_VALUES_PER_CELL: usize = 7;
+
+// Below this Oklab chroma, the output is considered achromatic, and its hue undefined.
+const ACHROMATIC_CHROMA_THRESHOLD: f32 = 0.002;
+
+const COLOR_SPACES: [&str; 3] = ["oklab", "ictcp", "ipt"];
+
+/// Pushes a hue × exposure grid of saturated colors through techniques,
+/// and measures how far the output hue drifts from the input hue.
+pub struct HueAnalysis {
+    grid_shader: ShaderKey,
+    drift_shader: ShaderKey,
+    results_buffer: Buffer,
+}
+
+struct HueDriftCell {
+    hue_index: usize,
+    ev: f64,
+    // In Oklab, ICtCp, and IPT, in degrees.
+    input_hue: [f32; 3],
+    // `None` where the output is achromatic.
+    drift: Option<[f32; 3]>,
+}
+
+pub struct HueDriftReport {
+    technique_name: String,
+    cells: Vec<HueDriftCell>,
+}
+
+impl HueAnalysis {
+    pub fn new(shader_lib: &mut ShaderLib, gl: &gl::Gl) -> Self {
+        Self {
+            grid_shader: shader_lib.add_compute_shader("shaders/analysis/hue_grid.glsl"),
+            drift_shader: shader_lib.add_compute_shader("shaders/analysis/hue_drift.glsl"),
+            results_buffer: Buffer::new(
+                gl,
+                HUE_ANALYSIS_HUE_COUNT
+                    * HUE_ANALYSIS_EV_COUNT
+                    * HUE_ANALYSIS_VALUES_PER_CELL
+                    * std::mem::size_of::<f32>(),
+            ),
+        }
+    }
+
+    /// `draw_technique` must render the given input texture into the bound framebuffer at EV 0.
+    pub fn run(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        technique_name: String,
+        draw_technique: impl FnOnce(&Texture),
+    ) -> anyhow::Result<HueDriftReport> {
+        let grid_shader = shader_lib
+            .get_shader_gl_handle(&self.grid_shader)
+            .context("The hue grid shader failed to compile")?;
+        let drift_shader = shader_lib
+            .get_shader_gl_handle(&self.drift_shader)
+            .context("The hue drift shader failed to compile")?;
+
+        let size = [HUE_ANALYSIS_HUE_COUNT, HUE_ANALYSIS_EV_COUNT];
+        let grid = Texture::new_2d_empty(gl, size, gl::RGBA32F);
+
+        unsafe {
+            gl.UseProgram(grid_shader);
+            let loc = shader_lib.uniform_location(gl, grid_shader, "output_image");
+            gl.Uniform1i(loc, 0);
+            gl.BindImageTexture(0, grid.id, 0, gl::FALSE, 0, gl::WRITE_ONLY, gl::RGBA32F);
+            dispatch_compute(gl, grid_shader, [size[0] as u32, size[1] as u32, 1]);
+            gl.UseProgram(0);
+            gl.MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT);
+        }
+
+        // Float, so that the output isn't quantized before it's measured.
+        let fbo = Fbo::with_format(gl, size, gl::RGBA32F);
+        fbo.bind(gl);
+        draw_technique(&grid);
+        fbo.unbind(gl);
+
+        unsafe {
+            gl.UseProgram(drift_shader);
+
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.BindTexture(gl::TEXTURE_2D, grid.id);
+            let loc = shader_lib.uniform_location(gl, drift_shader, "input_texture");
+            gl.Uniform1i(loc, 0);
+
+            gl.ActiveTexture(gl::TEXTURE1);
+            gl.BindTexture(gl::TEXTURE_2D, fbo.texture.id);
+            let loc = shader_lib.uniform_location(gl, drift_shader, "output_texture");
+            gl.Uniform1i(loc, 1);
+
+            self.results_buffer.bind_storage(gl, 0);
+            dispatch_compute(gl, drift_shader, [size[0] as u32, size[1] as u32, 1]);
+
+            gl.UseProgram(0);
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+        }
+
+        fbo.destroy(gl);
+        grid.destroy(gl);
+
+        let results: Vec<f32> = self.results_buffer.read(gl);
+        let cells = results
+            .chunks_exact(HUE_ANALYSIS_VALUES_PER_CELL)
+            .enumerate()
+            .map(|(i, values)| {
+                let input_hue = [values[0], values[1], values[2]];
+                let output_hue = [values[3], values[4], values[5]];
+                let output_chroma = values[6];
+
+                HueDriftCell {
+                    hue_index: i % HUE_ANALYSIS_HUE_COUNT,
+                    ev: HUE_ANALYSIS_EV_MIN
+                        + (i / HUE_ANALYSIS_HUE_COUNT) as f64 * HUE_ANALYSIS_EV_STEP,
+                    input_hue,
+                    drift: (output_chroma >= ACHROMATIC_CHROMA_THRESHOLD).then(|| {
+                        [0, 1, 2].map(|space| wrap_degrees(output_hue[space] - input_hue[space]))
+                    }),
+                }
+            })
+            .collect();
+
+        Ok(HueDriftReport {
+            technique_name,
+            cells,
+        })
+    }
+}
+
+/// Wraps an angle difference into [-180, 180).
+fn wrap_degrees(angle: f32) -> f32 {
+    (angle + 180.0).rem_euclid(360.0) - 180.0
+}
+
+impl HueDriftReport {
+    /// Mean and max absolute drift per color space, over cells with a chromatic output.
+    fn summary(&self) -> [(f32, f32); 3] {
+        [0, 1, 2].map(|space| {
+            let drifts: Vec<f32> = self
+                .cells
+                .iter()
+                .filter_map(|cell| Some(cell.drift?[space].abs()))
+                .collect();
+
+            let mean = drifts.iter().sum::<f32>() / drifts.len().max(1) as f32;
+            let max = drifts.iter().copied().fold(0.0, f32::max);
+            (mean, max)
+        })
+    }
+
+    /// Writes `<technique>.csv`, with one row per grid cell, and `<technique>.json`,
+    /// with drift arrays indexed by `[hue][ev]` and a summary.
+    pub fn write(&self, dir: &Path) -> anyhow::Result<()> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Creating {:?}", dir))?;
+
+        let mut csv = String::new();
+        writeln!(
+            csv,
+            "hue_index,ev,input_hue_oklab,input_hue_ictcp,input_hue_ipt,drift_oklab,drift_ictcp,drift_ipt"
+        )?;
+        for cell in &self.cells {
+            write!(
+                csv,
+                "{},{},{},{},{}",
+                cell.hue_index, cell.ev, cell.input_hue[0], cell.input_hue[1], cell.input_hue[2]
+            )?;
+            for space in 0..3 {
+                // Empty where the output is achromatic.
+                match cell.drift {
+                    Some(drift) => write!(csv, ",{}", drift[space])?,
+                    None => write!(csv, ",")?,
+                }
+            }
+            writeln!(csv)?;
+        }
+
+        let cell = |hue_index: usize, ev_index: usize| {
+            &self.cells[ev_index * HUE_ANALYSIS_HUE_COUNT + hue_index]
+        };
+        let json_array = |values: Vec<String>| format!("[{}]", values.join(", "));
+
+        let mut json = String::new();
+        writeln!(json, "{{")?;
+        writeln!(json, "  \"technique\": {:?},", self.technique_name)?;
+        writeln!(
+            json,
+            "  \"evs\": {},",
+            json_array(
+                (0..HUE_ANALYSIS_EV_COUNT)
+                    .map(|ev_index| cell(0, ev_index).ev.to_string())
+                    .collect()
+            )
+        )?;
+        writeln!(
+            json,
+            "  \"input_hue_oklab\": {},",
+            json_array(
+                (0..HUE_ANALYSIS_HUE_COUNT)
+                    .map(|hue_index| cell(hue_index, 0).input_hue[0].to_string())
+                    .collect()
+            )
+        )?;
+
+        writeln!(json, "  \"drift\": {{")?;
+        for (space, space_name) in COLOR_SPACES.iter().enumerate() {
+            let per_hue: Vec<String> = (0..HUE_ANALYSIS_HUE_COUNT)
+                .map(|hue_index| {
+                    json_array(
+                        (0..HUE_ANALYSIS_EV_COUNT)
+                            .map(|ev_index| {
+                                cell(hue_index, ev_index)
+                                    .drift
+                                    .map_or_else(|| "null".to_owned(), |d| d[space].to_string())
+                            })
+                            .collect(),
+                    )
+                })
+                .collect();
+
+            let separator = if space + 1 < COLOR_SPACES.len() {
+                ","
+            } else {
+                ""
+            };
+            writeln!(
+                json,
+                "    {:?}: {}{}",
+                space_name,
+                json_array(per_hue),
+                separator
+            )?;
+        }
+        writeln!(json, "  }},")?;
+
+        writeln!(json, "  \"summary\": {{")?;
+        let summary = self.summary();
+        for (space, space_name) in COLOR_SPACES.iter().enumerate() {
+            let (mean, max) = summary[space];
+            let separator = if space + 1 < COLOR_SPACES.len() {
+                ","
+            } else {
+                ""
+            };
+            writeln!(
+                json,
+                "    {:?}: {{\"mean_abs_drift\": {}, \"max_abs_drift\": {}}}{}",
+                space_name, mean, max, separator
+            )?;
+        }
+        writeln!(json, "  }}")?;
+        writeln!(json, "}}")?;
+
+        for (extension, contents) in [("csv", csv), ("json", json)] {
+            let file_path = dir.join(format!("{}.{}", self.technique_name, extension));
+            std::fs::write(&file_path, contents)
+                .with_context(|| format!("Writing {:?}", file_path))?;
+            log::info!("Saved {:?}", file_path);
+        }
+
+        let [oklab, ictcp, ipt] = summary;
+        log::info!(
+            "{}: mean/max abs hue drift: Oklab {:.2}/{:.2}, ICtCp {:.2}/{:.2}, IPT {:.2}/{:.2} degrees",
+            self.technique_name,
+            oklab.0,
+            oklab.1,
+            ictcp.0,
+            ictcp.1,
+            ipt.0,
+            ipt.1
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use crate::output_target::DisplayPrimaries;
+
+// ICC PCS illuminant.
+const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+const BRADFORD: [[f64; 3]; 3] = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+/// Builds an ICC v4 display profile with the sRGB transfer function and the given primaries,
+/// for tagging SDR captures.
+pub fn display_profile(primaries: DisplayPrimaries) -> Vec<u8> {
+    let description = match primaries {
+        DisplayPrimaries::Bt709 => "sRGB",
+        DisplayPrimaries::P3 => "Display P3",
+        DisplayPrimaries::Bt2020 => "BT.2020 (sRGB transfer)",
+    };
+
+    let [red, green, blue, white] = primaries.chromaticities().map(|[x, y]| xy_to_xyz(x, y));
+    let rgb_to_xyz = rgb_to_xyz_matrix([red, green, blue], white);
+    let adaptation = bradford_adaptation(white, D50);
+    let rgb_to_pcs = mat_mul(&adaptation, &rgb_to_xyz);
+    let column = |i: usize| [rgb_to_pcs[0][i], rgb_to_pcs[1][i], rgb_to_pcs[2][i]];
+
+    // The sRGB EOTF, as `Y = (aX + b)^g` above `d`, and `Y = cX` below it.
+    let trc = parametric_curve_tag([2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045]);
+
+    let mut tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", text_tag(description)),
+        (b"cprt", text_tag("No copyright, use freely")),
+        (b"wtpt", xyz_tag(D50)),
+        (b"chad", sf32_tag(&adaptation)),
+        (b"rXYZ", xyz_tag(column(0))),
+        (b"gXYZ", xyz_tag(column(1))),
+        (b"bXYZ", xyz_tag(column(2))),
+    ];
+    for signature in [b"rTRC", b"gTRC", b"bTRC"] {
+        tags.push((signature, trc.clone()));
+    }
+
+    let header_size = 128;
+    let tag_table_size = 4 + 12 * tags.len();
+
+    let mut tag_table = Vec::new();
+    let mut tag_data = Vec::new();
+    push_u32(&mut tag_table, tags.len() as u32);
+    for (signature, data) in &tags {
+        tag_table.extend_from_slice(*signature);
+        push_u32(
+            &mut tag_table,
+            (header_size + tag_table_size + tag_data.len()) as u32,
+        );
+        push_u32(&mut tag_table, data.len() as u32);
+
+        tag_data.extend_from_slice(data);
+        // Tags start on 4-byte boundaries.
+        tag_data.resize((tag_data.len() + 3) & !3, 0);
+    }
+
+    let profile_size = header_size + tag_table_size + tag_data.len();
+
+    let mut profile = Vec::with_capacity(profile_size);
+    push_u32(&mut profile, profile_size as u32);
+    profile.extend_from_slice(&[0; 4]); // Preferred CMM
+    push_u32(&mut profile, 0x04300000); // Version 4.3
+    profile.extend_from_slice(b"mntr");
+    profile.extend_from_slice(b"RGB ");
+    profile.extend_from_slice(b"XYZ ");
+    for date_part in [2022, 1, 1, 0, 0, 0] {
+        profile.extend_from_slice(&(date_part as u16).to_be_bytes());
+    }
+    profile.extend_from_slice(b"acsp");
+    // Platform, flags, manufacturer, model, attributes, and the perceptual intent.
+    profile.extend_from_slice(&[0; 28]);
+    push_xyz(&mut profile, D50);
+    // Creator, profile ID (optional), and reserved bytes.
+    profile.extend_from_slice(&[0; 48]);
+    debug_assert_eq!(profile.len(), header_size);
+
+    profile.extend_from_slice(&tag_table);
+    profile.extend_from_slice(&tag_data);
+    profile
+}
+
+fn push_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn push_s15_fixed16(out: &mut Vec<u8>, v: f64) {
+    out.extend_from_slice(&((v * 65536.0).round() as i32).to_be_bytes());
+}
+
+fn push_xyz(out: &mut Vec<u8>, xyz: [f64; 3]) {
+    for v in xyz {
+        push_s15_fixed16(out, v);
+    }
+}
+
+fn tag_header(signature: &[u8; 4]) -> Vec<u8> {
+    let mut out = signature.to_vec();
+    out.extend_from_slice(&[0; 4]);
+    out
+}
+
+/// `multiLocalizedUnicodeType` with a single en-US record.
+fn text_tag(text: &str) -> Vec<u8> {
+    let utf16: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+
+    let mut out = tag_header(b"mluc");
+    push_u32(&mut out, 1); // Record count
+    push_u32(&mut out, 12); // Record size
+    out.extend_from_slice(b"enUS");
+    push_u32(&mut out, utf16.len() as u32);
+    push_u32(&mut out, 28); // Offset of the string
+    out.extend_from_slice(&utf16);
+    out
+}
+
+fn xyz_tag(xyz: [f64; 3]) -> Vec<u8> {
+    let mut out = tag_header(b"XYZ ");
+    push_xyz(&mut out, xyz);
+    out
+}
+
+fn sf32_tag(m: &[[f64; 3]; 3]) -> Vec<u8> {
+    let mut out = tag_header(b"sf32");
+    for v in m.iter().flatten() {
+        push_s15_fixed16(&mut out, *v);
+    }
+    out
+}
+
+/// `parametricCurveType` function 3: `[g, a, b, c, d]`.
+fn parametric_curve_tag(params: [f64; 5]) -> Vec<u8> {
+    let mut out = tag_header(b"para");
+    out.extend_from_slice(&3u16.to_be_bytes());
+    out.extend_from_slice(&[0; 2]);
+    for v in params {
+        push_s15_fixed16(&mut out, v);
+    }
+    out
+}
+
+fn xy_to_xyz(x: f32, y: f32) -> [f64; 3] {
+    let (x, y) = (x as f64, y as f64);
+    [x / y, 1.0, (1.0 - x - y) / y]
+}
+
+/// Columns are the primaries, scaled so that RGB 1 maps to `white`.
+fn rgb_to_xyz_matrix(primaries: [[f64; 3]; 3], white: [f64; 3]) -> [[f64; 3]; 3] {
+    let m = [0, 1, 2].map(|row| primaries.map(|p| p[row]));
+    let scale = mat_mul_vec(&mat_inverse(&m), white);
+    [0, 1, 2].map(|row| [0, 1, 2].map(|col| m[row][col] * scale[col]))
+}
+
+fn bradford_adaptation(src_white: [f64; 3], dst_white: [f64; 3]) -> [[f64; 3]; 3] {
+    let src = mat_mul_vec(&BRADFORD, src_white);
+    let dst = mat_mul_vec(&BRADFORD, dst_white);
+    let scale = [0, 1, 2].map(|row| [0, 1, 2].map(|col| BRADFORD[row][col] * dst[row] / src[row]));
+    mat_mul(&mat_inverse(&BRADFORD), &scale)
+}
+
+fn mat_mul(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    [0, 1, 2].map(|row| [0, 1, 2].map(|col| (0..3).map(|i| a[row][i] * b[i][col]).sum()))
+}
+
+fn mat_mul_vec(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+fn mat_inverse(m: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let cofactor = |row: usize, col: usize| {
+        let (r0, r1) = ((row + 1) % 3, (row + 2) % 3);
+        let (c0, c1) = ((col + 1) % 3, (col + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+
+    let det = (0..3).map(|col| m[0][col] * cofactor(0, col)).sum::<f64>();
+    [0, 1, 2].map(|row| [0, 1, 2].map(|col| cofactor(col, row) / det))
+}
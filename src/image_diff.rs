@@ -0,0 +1,217 @@
+use anyhow::Context;
+use std::{fmt::Write as _, path::Path};
+
+use crate::{
+    shader::{dispatch_compute, ShaderKey},
+    shader_lib::ShaderLib,
+    texture::Texture,
+};
+
+// Must match the `DIFF_METRIC_*` defines in `shaders/analysis/diff_heatmap.glsl`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DiffMetric {
+    Ciede2000 = 0,
+    Itp = 1,
+}
+
+impl DiffMetric {
+    pub const ALL: [Self; 2] = [Self::Ciede2000, Self::Itp];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ciede2000 => "ΔE 2000",
+            Self::Itp => "ΔE-ITP",
+        }
+    }
+
+    /// For file names.
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Self::Ciede2000 => "de2000",
+            Self::Itp => "deitp",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct DeltaEStats {
+    pub mean: f32,
+    pub median: f32,
+    pub p95: f32,
+    pub max: f32,
+}
+
+impl DeltaEStats {
+    fn from_values(mut values: Vec<f32>) -> Self {
+        if values.is_empty() {
+            return Default::default();
+        }
+
+        values.sort_by(f32::total_cmp);
+        let percentile = |p: f32| values[((values.len() - 1) as f32 * p).round() as usize];
+
+        Self {
+            mean: values.iter().sum::<f32>() / values.len() as f32,
+            median: percentile(0.5),
+            p95: percentile(0.95),
+            max: values[values.len() - 1],
+        }
+    }
+}
+
+/// Per-pixel ΔE between two images, one value per `DiffMetric`.
+pub struct ImageDiff {
+    pub texture: Texture,
+}
+
+impl ImageDiff {
+    /// Reads back the ΔE values, and summarizes them for each metric.
+    pub fn stats(&self, gl: &gl::Gl) -> [DeltaEStats; 2] {
+        let [width, height] = self.texture.size;
+        let mut texels = vec![[0.0f32; 4]; width * height];
+
+        unsafe {
+            gl.GetTextureImage(
+                self.texture.id,
+                0,
+                gl::RGBA,
+                gl::FLOAT,
+                (texels.len() * std::mem::size_of::<[f32; 4]>()) as _,
+                texels.as_mut_ptr() as *mut std::ffi::c_void,
+            );
+        }
+
+        DiffMetric::ALL.map(|metric| {
+            DeltaEStats::from_values(texels.iter().map(|t| t[metric as usize]).collect())
+        })
+    }
+
+    pub fn destroy(self, gl: &gl::Gl) {
+        self.texture.destroy(gl);
+    }
+}
+
+/// Computes `ImageDiff`s, and draws them as heatmaps.
+pub struct ImageDiffer {
+    compute_shader: ShaderKey,
+    heatmap_shader: ShaderKey,
+}
+
+impl ImageDiffer {
+    pub fn new(shader_lib: &mut ShaderLib) -> Self {
+        Self {
+            compute_shader: shader_lib.add_compute_shader("shaders/analysis/image_diff.glsl"),
+            heatmap_shader: shader_lib.add_shader("shaders/analysis/diff_heatmap.glsl"),
+        }
+    }
+
+    /// `image_a` must be a technique output. `image_b` is either another one,
+    /// or a reference image of the same size, stored top row first.
+    pub fn compute(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        image_a: &Texture,
+        image_b: &Texture,
+        image_b_is_reference: bool,
+    ) -> Option<ImageDiff> {
+        let shader_program = shader_lib.get_shader_gl_handle(&self.compute_shader)?;
+        let texture = Texture::new_2d_empty(gl, image_a.size, gl::RGBA32F);
+
+        unsafe {
+            gl.UseProgram(shader_program);
+
+            for (unit, (image, name)) in [(image_a, "image_a"), (image_b, "image_b")]
+                .into_iter()
+                .enumerate()
+            {
+                gl.ActiveTexture(gl::TEXTURE0 + unit as u32);
+                gl.BindTexture(gl::TEXTURE_2D, image.id);
+                let loc = shader_lib.uniform_location(gl, shader_program, name);
+                gl.Uniform1i(loc, unit as i32);
+            }
+
+            let loc = shader_lib.uniform_location(gl, shader_program, "flip_image_b");
+            gl.Uniform1i(loc, image_b_is_reference as i32);
+
+            let loc = shader_lib.uniform_location(gl, shader_program, "output_image");
+            gl.Uniform1i(loc, 0);
+            gl.BindImageTexture(0, texture.id, 0, gl::FALSE, 0, gl::WRITE_ONLY, gl::RGBA32F);
+
+            dispatch_compute(
+                gl,
+                shader_program,
+                [texture.size[0] as u32, texture.size[1] as u32, 1],
+            );
+
+            gl.UseProgram(0);
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT | gl::TEXTURE_UPDATE_BARRIER_BIT);
+        }
+
+        Some(ImageDiff { texture })
+    }
+
+    /// Draws `diff` as a heatmap into the bound framebuffer, which must be the same size.
+    pub fn draw_heatmap(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        diff: &ImageDiff,
+        metric: DiffMetric,
+    ) {
+        let shader_program = match shader_lib.get_shader_gl_handle(&self.heatmap_shader) {
+            Some(shader_program) => shader_program,
+            None => return,
+        };
+
+        unsafe {
+            gl.Viewport(0, 0, diff.texture.size[0] as _, diff.texture.size[1] as _);
+            gl.UseProgram(shader_program);
+
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.BindTexture(gl::TEXTURE_2D, diff.texture.id);
+            let loc = shader_lib.uniform_location(gl, shader_program, "diff_texture");
+            gl.Uniform1i(loc, 0);
+
+            let loc = shader_lib.uniform_location(gl, shader_program, "diff_metric");
+            gl.Uniform1i(loc, metric as i32);
+
+            gl.DrawArrays(gl::TRIANGLES, 0, 3);
+            gl.UseProgram(0);
+        }
+    }
+}
+
+/// Writes `[metric, mean, median, p95, max]` rows to a CSV file.
+pub fn write_diff_stats(stats: &[DeltaEStats; 2], file_path: &Path) -> anyhow::Result<()> {
+    let mut csv = String::new();
+    writeln!(csv, "metric,mean,median,p95,max")?;
+    for (metric, stats) in DiffMetric::ALL.iter().zip(stats) {
+        writeln!(
+            csv,
+            "{},{},{},{},{}",
+            metric.short_name(),
+            stats.mean,
+            stats.median,
+            stats.p95,
+            stats.max
+        )?;
+    }
+
+    std::fs::write(file_path, csv).with_context(|| format!("Writing {:?}", file_path))
+}
+
+pub fn log_diff_stats(label: &str, stats: &[DeltaEStats; 2]) {
+    for (metric, stats) in DiffMetric::ALL.iter().zip(stats) {
+        log::info!(
+            "{}: {} mean {:.2}, median {:.2}, p95 {:.2}, max {:.2}",
+            label,
+            metric.name(),
+            stats.mean,
+            stats.median,
+            stats.p95,
+            stats.max
+        );
+    }
+}
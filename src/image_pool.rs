@@ -84,6 +84,10 @@ impl ImagePool {
         self.images.get(idx).map(|img| &img.path)
     }
 
+    pub fn find_image(&self, path: &Path) -> Option<usize> {
+        self.images.iter().position(|img| img.path == path)
+    }
+
     pub fn image_count(&self) -> usize {
         self.images.len()
     }
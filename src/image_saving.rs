@@ -0,0 +1,219 @@
+use anyhow::Context;
+use exr::{meta::attribute::Chromaticities, prelude::*};
+use jpeg_encoder::{ColorType, Encoder};
+use std::{ffi::c_void, fs::File, io::BufWriter, path::Path, str::FromStr};
+
+use crate::{
+    output_target::{OutputEncoding, OutputTarget},
+    provenance::Provenance,
+};
+
+/// File format of batch captures.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat {
+    /// 8-bit, sRGB only, with BT.709 primaries.
+    Jpeg,
+    /// 16 bits per channel, tagged with the output target's `cICP`.
+    Png,
+    /// 32-bit float; the framebuffer contents as-is, e.g. PQ code values for AVIF encoders.
+    /// Tagged with the display primaries.
+    Exr,
+}
+
+impl CaptureFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Exr => "exr",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .parse()
+            .with_context(|| format!("Unsupported capture file {:?}", path))
+    }
+
+    pub fn framebuffer_format(self) -> gl::types::GLenum {
+        match self {
+            Self::Jpeg => gl::SRGB8_ALPHA8,
+            // Float, so that the output isn't quantized twice.
+            Self::Png | Self::Exr => gl::RGBA32F,
+        }
+    }
+
+    pub fn ensure_supports(self, output_target: OutputTarget) -> anyhow::Result<()> {
+        match (self, output_target) {
+            // JPEG captures aren't tagged, so they must be plain sRGB.
+            (Self::Jpeg, target) if target != OutputTarget::SRGB => Err(anyhow::anyhow!(
+                "JPEG can't hold {}; use png or exr",
+                target
+            )),
+            (Self::Png, target) if matches!(target.encoding, OutputEncoding::ScRgb { .. }) => {
+                Err(anyhow::anyhow!("PNG can't hold {}; use exr", target))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for CaptureFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "jpg" | "jpeg" => Ok(Self::Jpeg),
+            "png" => Ok(Self::Png),
+            "exr" => Ok(Self::Exr),
+            _ => Err(anyhow::anyhow!(
+                "Unknown image format {:?}; expected jpg, png, or exr",
+                s
+            )),
+        }
+    }
+}
+
+/// Reads back the bound read framebuffer, which must be float, top row first.
+pub fn read_framebuffer_rgb(gl: &gl::Gl, size: [usize; 2]) -> Vec<[f32; 3]> {
+    let mut pixels = vec![[0f32; 4]; size[0] * size[1]];
+
+    unsafe {
+        gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl.ReadPixels(
+            0,
+            0,
+            size[0] as _,
+            size[1] as _,
+            gl::RGBA,
+            gl::FLOAT,
+            pixels.as_mut_ptr() as *mut c_void,
+        );
+    }
+
+    pixels
+        .chunks_exact(size[0])
+        .rev()
+        .flatten()
+        .map(|px| [px[0], px[1], px[2]])
+        .collect()
+}
+
+/// Writes an 8-bit RGBA JPEG of `pixels` (top row first), with `icc_profile`
+/// and the XMP of `provenance` embedded.
+pub fn write_jpeg(
+    file_path: &Path,
+    size: [usize; 2],
+    pixels: &[u8],
+    icc_profile: &[u8],
+    provenance: Option<&Provenance>,
+) -> anyhow::Result<()> {
+    let mut encoder = Encoder::new_file(file_path, 90)
+        .with_context(|| format!("Failed to create {:?}", file_path))?;
+
+    // A single chunk, numbered 1 of 1; the profiles are far smaller than a segment.
+    let mut icc_segment = b"ICC_PROFILE\0\x01\x01".to_vec();
+    icc_segment.extend_from_slice(icc_profile);
+    encoder
+        .add_app_segment(2, &icc_segment)
+        .context("Embedding the ICC profile")?;
+
+    if let Some(provenance) = provenance {
+        let mut xmp_segment = b"http://ns.adobe.com/xap/1.0/\0".to_vec();
+        xmp_segment.extend_from_slice(provenance.to_xmp().as_bytes());
+        encoder
+            .add_app_segment(1, &xmp_segment)
+            .context("Embedding the XMP")?;
+    }
+
+    encoder
+        .encode(pixels, size[0] as _, size[1] as _, ColorType::Rgba)
+        .context("encoder.encode")
+}
+
+/// Writes a 16-bit RGB PNG of `pixels` (top row first, in 0..1), tagged with `cicp`
+/// and `icc_profile` if given, and with the XMP of `provenance` embedded.
+pub fn write_png16(
+    file_path: &Path,
+    size: [usize; 2],
+    pixels: &[[f32; 3]],
+    cicp: Option<[u8; 4]>,
+    icc_profile: Option<&[u8]>,
+    provenance: Option<&Provenance>,
+) -> anyhow::Result<()> {
+    let file = File::create(file_path).with_context(|| format!("Creating {:?}", file_path))?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), size[0] as u32, size[1] as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Sixteen);
+
+    let mut writer = encoder.write_header()?;
+    if let Some(cicp) = cicp {
+        writer.write_chunk(png::chunk::ChunkType(*b"cICP"), &cicp)?;
+    }
+    if let Some(icc_profile) = icc_profile {
+        // Profile name, and zlib compression.
+        let mut iccp = b"ICC profile\0\0".to_vec();
+        iccp.extend(miniz_oxide::deflate::compress_to_vec_zlib(icc_profile, 6));
+        writer.write_chunk(png::chunk::ChunkType(*b"iCCP"), &iccp)?;
+    }
+    if let Some(provenance) = provenance {
+        // Keyword, uncompressed, with empty language and translated keyword.
+        let mut itxt = b"XML:com.adobe.xmp\0\0\0\0\0".to_vec();
+        itxt.extend_from_slice(provenance.to_xmp().as_bytes());
+        writer.write_chunk(png::chunk::ChunkType(*b"iTXt"), &itxt)?;
+    }
+
+    // Big-endian samples.
+    let data: Vec<u8> = pixels
+        .iter()
+        .flatten()
+        .flat_map(|v| ((v.clamp(0.0, 1.0) * 65535.0).round() as u16).to_be_bytes())
+        .collect();
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Writes a float RGB EXR of `pixels`, top row first, tagged with `chromaticities`
+/// (CIE xy of red, green, blue, and white), and with the fields of `provenance`
+/// as `notorious6:*` string attributes.
+pub fn write_exr(
+    file_path: &Path,
+    size: [usize; 2],
+    pixels: &[[f32; 3]],
+    chromaticities: [[f32; 2]; 4],
+    provenance: Option<&Provenance>,
+) -> anyhow::Result<()> {
+    let channels = SpecificChannels::rgb(|pos: Vec2<usize>| {
+        let [r, g, b] = pixels[pos.y() * size[0] + pos.x()];
+        (r, g, b)
+    });
+
+    let mut image = Image::from_channels((size[0], size[1]), channels);
+    let [red, green, blue, white] = chromaticities.map(|[x, y]| Vec2(x, y));
+    image.attributes.chromaticities = Some(Chromaticities {
+        red,
+        green,
+        blue,
+        white,
+    });
+
+    for (name, value) in provenance.map(Provenance::fields).unwrap_or_default() {
+        // EXR text is Latin-1; anything else is left out.
+        if let Some(value) = Text::new_or_none(value) {
+            image.attributes.other.insert(
+                Text::new_or_panic(format!("notorious6:{}", name)),
+                AttributeValue::Text(value),
+            );
+        }
+    }
+
+    image
+        .write()
+        .to_file(file_path)
+        .with_context(|| format!("Writing {:?}", file_path))
+}
@@ -0,0 +1,270 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::{
+    buffer::Buffer,
+    shader::{dispatch_compute, ShaderKey},
+    shader_lib::ShaderLib,
+    texture::Texture,
+};
+
+// Must match the `LUMINANCE_HISTOGRAM_*` defines in `shaders/stats/luminance_histogram_common.glsl`
+const LUMINANCE_HISTOGRAM_BIN_COUNT: usize = 256;
+const LUMINANCE_HISTOGRAM_LOG2_MIN: f64 = -16.0;
+const LUMINANCE_HISTOGRAM_LOG2_MAX: f64 = 16.0;
+
+const MID_GRAY: f64 = 0.18;
+
+// Uniform buffer binding of `ImageStats` in `shaders/inc/image_stats.glsl`
+const IMAGE_STATS_BINDING: u32 = 1;
+pub const IMAGE_STATS_BLOCK: &str = "ImageStats";
+
+/// Histogram of the log2 luminance of an image at EV 0.
+#[derive(Clone)]
+pub struct LuminanceHistogram {
+    bins: Vec<u32>,
+}
+
+impl LuminanceHistogram {
+    fn bin_log2_luminance(bin: usize) -> f64 {
+        LUMINANCE_HISTOGRAM_LOG2_MIN
+            + (bin as f64 + 0.5) * (LUMINANCE_HISTOGRAM_LOG2_MAX - LUMINANCE_HISTOGRAM_LOG2_MIN)
+                / LUMINANCE_HISTOGRAM_BIN_COUNT as f64
+    }
+
+    fn total_count(&self) -> u64 {
+        self.bins.iter().map(|&count| count as u64).sum()
+    }
+
+    /// Index of the bin containing the given fraction of pixels, counting from the darkest.
+    fn percentile_bin(&self, fraction: f64) -> usize {
+        let target = (self.total_count() as f64 * fraction.clamp(0.0, 1.0)) as u64;
+        let mut cumulative = 0u64;
+
+        for (bin, &count) in self.bins.iter().enumerate() {
+            cumulative += count as u64;
+            if cumulative > target {
+                return bin;
+            }
+        }
+
+        self.bins.len() - 1
+    }
+
+    /// Log2 of the geometric mean luminance of pixels between the `low` and `high` percentiles.
+    /// Trimming the extremes keeps black borders and specular highlights from skewing the result.
+    pub fn log2_trimmed_geometric_mean(&self, low: f64, high: f64) -> Option<f64> {
+        let first_bin = self.percentile_bin(low);
+        let last_bin = self.percentile_bin(high);
+
+        let (weighted_sum, count) =
+            (first_bin..=last_bin).fold((0.0, 0u64), |(sum, count), bin| {
+                let bin_count = self.bins[bin] as u64;
+                (
+                    sum + Self::bin_log2_luminance(bin) * bin_count as f64,
+                    count + bin_count,
+                )
+            });
+
+        (count > 0).then(|| weighted_sum / count as f64)
+    }
+
+    /// The EV which maps the key value of the image to mid-gray.
+    pub fn auto_exposure_ev(&self) -> Option<f64> {
+        self.log2_trimmed_geometric_mean(0.05, 0.95)
+            .map(|log2_key| MID_GRAY.log2() - log2_key)
+    }
+}
+
+/// Of an image at EV 0.
+#[derive(Clone)]
+pub struct ImageStatistics {
+    pub histogram: LuminanceHistogram,
+    /// Of the darkest pixel which isn't black; 0 if they all are.
+    pub min_luminance: f32,
+    pub max_luminance: f32,
+    /// Largest R, G, or B.
+    pub max_component: f32,
+}
+
+/// `ImageStatistics` as seen by techniques.
+// Must match `ImageStats` in `shaders/inc/image_stats.glsl`, with std140 layout
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ImageStatsConstants {
+    log2_min: f32,
+    log2_max: f32,
+    log2_average: f32,
+    max_component: f32,
+    luminance_cdf: [f32; LUMINANCE_HISTOGRAM_BIN_COUNT],
+}
+
+impl ImageStatistics {
+    /// log2 of the geometric mean luminance.
+    pub fn log2_average(&self) -> f64 {
+        self.histogram
+            .log2_trimmed_geometric_mean(0.0, 1.0)
+            .unwrap_or(LUMINANCE_HISTOGRAM_LOG2_MIN)
+    }
+
+    fn constants(&self) -> ImageStatsConstants {
+        let log2_or_min = |luminance: f32| {
+            if luminance > 0.0 {
+                luminance.log2()
+            } else {
+                LUMINANCE_HISTOGRAM_LOG2_MIN as f32
+            }
+        };
+
+        let total_count = self.histogram.total_count().max(1) as f64;
+        let mut luminance_cdf = [0.0; LUMINANCE_HISTOGRAM_BIN_COUNT];
+        let mut cumulative = 0u64;
+        for (cdf, &count) in luminance_cdf.iter_mut().zip(&self.histogram.bins) {
+            cumulative += count as u64;
+            *cdf = (cumulative as f64 / total_count) as f32;
+        }
+
+        ImageStatsConstants {
+            log2_min: log2_or_min(self.min_luminance),
+            log2_max: log2_or_min(self.max_luminance),
+            log2_average: self.log2_average() as f32,
+            max_component: self.max_component,
+            luminance_cdf,
+        }
+    }
+}
+
+/// Image-wide statistics of the input stimulus, computed on the GPU.
+pub struct ImageStats {
+    compute_shader: ShaderKey,
+    bins_buffer: Buffer,
+    extremes_buffer: Buffer,
+    constants_buffer: Buffer,
+    cache: RefCell<HashMap<StatsCacheKey, ImageStatistics>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum StatsCacheKey {
+    // Images don't change once loaded.
+    Image(usize),
+    // Synthetic inputs; forgotten before they're destroyed, as texture ids get reused.
+    Texture(u32),
+}
+
+impl ImageStats {
+    pub fn new(shader_lib: &mut ShaderLib, gl: &gl::Gl) -> Self {
+        let compute_shader =
+            shader_lib.add_compute_shader("shaders/stats/luminance_histogram.glsl");
+
+        let bins_buffer = Buffer::new(
+            gl,
+            LUMINANCE_HISTOGRAM_BIN_COUNT * std::mem::size_of::<u32>(),
+        );
+
+        Self {
+            compute_shader,
+            bins_buffer,
+            extremes_buffer: Buffer::new(gl, 3 * std::mem::size_of::<u32>()),
+            constants_buffer: Buffer::new(gl, std::mem::size_of::<ImageStatsConstants>()),
+            cache: Default::default(),
+        }
+    }
+
+    /// Of the image at `image_index` in the pool, or of a synthetic `input` if `None`.
+    /// Cached; synthetic inputs must be passed to `forget` before they're destroyed.
+    /// Returns `None` if the shader hasn't been compiled yet.
+    pub fn statistics(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        input: &Texture,
+        image_index: Option<usize>,
+    ) -> Option<ImageStatistics> {
+        let key = image_index.map_or(StatsCacheKey::Texture(input.id), StatsCacheKey::Image);
+        if let Some(stats) = self.cache.borrow().get(&key) {
+            return Some(stats.clone());
+        }
+
+        let stats = self.compute(gl, shader_lib, input)?;
+        self.cache.borrow_mut().insert(key, stats.clone());
+        Some(stats)
+    }
+
+    /// Drops the cached statistics of a synthetic input.
+    pub fn forget(&self, input: &Texture) {
+        self.cache
+            .borrow_mut()
+            .remove(&StatsCacheKey::Texture(input.id));
+    }
+
+    /// Uploads `stats` from `statistics`, and binds them for the `ImageStats` uniform block.
+    /// All zero if `None`.
+    pub fn bind(&self, gl: &gl::Gl, stats: Option<&ImageStatistics>) {
+        let constants = match stats {
+            Some(stats) => stats.constants(),
+            None => ImageStatsConstants {
+                log2_min: 0.0,
+                log2_max: 0.0,
+                log2_average: 0.0,
+                max_component: 0.0,
+                luminance_cdf: [0.0; LUMINANCE_HISTOGRAM_BIN_COUNT],
+            },
+        };
+
+        self.constants_buffer
+            .write(gl, std::slice::from_ref(&constants));
+        self.constants_buffer.bind_uniform(gl, IMAGE_STATS_BINDING);
+    }
+
+    fn compute(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        input: &Texture,
+    ) -> Option<ImageStatistics> {
+        let shader_program = shader_lib.get_shader_gl_handle(&self.compute_shader)?;
+
+        self.bins_buffer.clear(gl);
+        // Min, max, and max; must match `extremes_buffer` in `shaders/stats/luminance_histogram.glsl`
+        self.extremes_buffer.write(gl, &[u32::MAX, 0, 0]);
+
+        unsafe {
+            gl.UseProgram(shader_program);
+
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.BindTexture(gl::TEXTURE_2D, input.id);
+            let loc = shader_lib.uniform_location(gl, shader_program, "input_texture");
+            gl.Uniform1i(loc, 0);
+
+            self.bins_buffer.bind_storage(gl, 0);
+            self.extremes_buffer.bind_storage(gl, 1);
+
+            dispatch_compute(
+                gl,
+                shader_program,
+                [input.size[0] as u32, input.size[1] as u32, 1],
+            );
+
+            gl.UseProgram(0);
+            gl.MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+        }
+
+        let [min_luminance_bits, max_luminance_bits, max_component_bits]: [u32; 3] = self
+            .extremes_buffer
+            .read(gl)
+            .try_into()
+            .expect("extremes_buffer");
+
+        Some(ImageStatistics {
+            histogram: LuminanceHistogram {
+                bins: self.bins_buffer.read(gl),
+            },
+            min_luminance: if min_luminance_bits == u32::MAX {
+                0.0
+            } else {
+                f32::from_bits(min_luminance_bits)
+            },
+            max_luminance: f32::from_bits(max_luminance_bits),
+            max_component: f32::from_bits(max_component_bits),
+        })
+    }
+}
@@ -0,0 +1,418 @@
+use anyhow::Context;
+use glutin::event::{ModifiersState, VirtualKeyCode};
+use std::{collections::HashMap, path::Path};
+
+pub const DEFAULT_KEY_BINDINGS_FILE: &str = "notorious6.keys";
+
+/// Something the viewer can be asked to do from the keyboard.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    PrevImage,
+    NextImage,
+    PrevTechnique,
+    NextTechnique,
+    EvUp,
+    EvDown,
+    ResetEv,
+    ToggleAutoExposure,
+    ToggleAdaptation,
+    ToggleHistogram,
+    CycleWaveform,
+    CycleVectorscope,
+    ToggleChromaticity,
+    ToggleToneCurve,
+    PinToneCurve,
+    CycleLutPlot,
+    ToggleDiffBaseline,
+    CycleDiffView,
+    CycleDebugView,
+    CycleDisplayPrimaries,
+    /// Of the technique output, at the image resolution.
+    Screenshot,
+    /// Of the window, with the overlays.
+    ScreenshotWindow,
+    /// 1 to 9.
+    StoreBookmark(u8),
+    RecallBookmark(u8),
+}
+
+const ACTION_NAMES: [(Action, &str); 40] = [
+    (Action::PrevImage, "prev_image"),
+    (Action::NextImage, "next_image"),
+    (Action::PrevTechnique, "prev_technique"),
+    (Action::NextTechnique, "next_technique"),
+    (Action::EvUp, "ev_up"),
+    (Action::EvDown, "ev_down"),
+    (Action::ResetEv, "reset_ev"),
+    (Action::ToggleAutoExposure, "toggle_auto_exposure"),
+    (Action::ToggleAdaptation, "toggle_adaptation"),
+    (Action::ToggleHistogram, "toggle_histogram"),
+    (Action::CycleWaveform, "cycle_waveform"),
+    (Action::CycleVectorscope, "cycle_vectorscope"),
+    (Action::ToggleChromaticity, "toggle_chromaticity"),
+    (Action::ToggleToneCurve, "toggle_tone_curve"),
+    (Action::PinToneCurve, "pin_tone_curve"),
+    (Action::CycleLutPlot, "cycle_lut_plot"),
+    (Action::ToggleDiffBaseline, "toggle_diff_baseline"),
+    (Action::CycleDiffView, "cycle_diff_view"),
+    (Action::CycleDebugView, "cycle_debug_view"),
+    (Action::CycleDisplayPrimaries, "cycle_display_primaries"),
+    (Action::Screenshot, "screenshot"),
+    (Action::ScreenshotWindow, "screenshot_window"),
+    (Action::StoreBookmark(1), "store_bookmark_1"),
+    (Action::StoreBookmark(2), "store_bookmark_2"),
+    (Action::StoreBookmark(3), "store_bookmark_3"),
+    (Action::StoreBookmark(4), "store_bookmark_4"),
+    (Action::StoreBookmark(5), "store_bookmark_5"),
+    (Action::StoreBookmark(6), "store_bookmark_6"),
+    (Action::StoreBookmark(7), "store_bookmark_7"),
+    (Action::StoreBookmark(8), "store_bookmark_8"),
+    (Action::StoreBookmark(9), "store_bookmark_9"),
+    (Action::RecallBookmark(1), "recall_bookmark_1"),
+    (Action::RecallBookmark(2), "recall_bookmark_2"),
+    (Action::RecallBookmark(3), "recall_bookmark_3"),
+    (Action::RecallBookmark(4), "recall_bookmark_4"),
+    (Action::RecallBookmark(5), "recall_bookmark_5"),
+    (Action::RecallBookmark(6), "recall_bookmark_6"),
+    (Action::RecallBookmark(7), "recall_bookmark_7"),
+    (Action::RecallBookmark(8), "recall_bookmark_8"),
+    (Action::RecallBookmark(9), "recall_bookmark_9"),
+];
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        &[$((VirtualKeyCode::$key, stringify!($key))),*]
+    };
+}
+
+// The keys which can be bound, by their `VirtualKeyCode` names.
+#[rustfmt::skip]
+const KEY_NAMES: &[(VirtualKeyCode, &str)] = key_names![
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Left, Right, Up, Down, Home, End, PageUp, PageDown, Insert, Delete,
+    Space, Return, Tab, Back, Escape,
+    Minus, Equals, LBracket, RBracket, Semicolon, Apostrophe, Grave, Comma, Period, Slash,
+    Backslash,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide, NumpadDecimal, NumpadEnter,
+];
+
+const BOOKMARK_KEYS: [VirtualKeyCode; 9] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
+/// A key, with the modifiers which must be held for it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    key: VirtualKeyCode,
+    ctrl: bool,
+    shift: bool,
+    alt: bool,
+}
+
+/// Maps keys to `Action`s, and sets how the EV responds to the keyboard and mouse.
+pub struct KeyBindings {
+    bindings: HashMap<KeyChord, Action>,
+    /// Of `EvUp` and `EvDown`, and of a mouse wheel notch.
+    pub ev_step: f64,
+    /// EV per pixel of dragging with the left mouse button.
+    pub ev_drag_sensitivity: f64,
+}
+
+impl KeyChord {
+    pub fn new(key: VirtualKeyCode, modifiers: ModifiersState) -> Self {
+        Self {
+            key,
+            ctrl: modifiers.ctrl(),
+            shift: modifiers.shift(),
+            alt: modifiers.alt(),
+        }
+    }
+
+    fn plain(key: VirtualKeyCode) -> Self {
+        Self::new(key, ModifiersState::empty())
+    }
+
+    fn with_ctrl(key: VirtualKeyCode) -> Self {
+        Self::new(key, ModifiersState::CTRL)
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        use VirtualKeyCode::*;
+
+        let mut bindings: HashMap<KeyChord, Action> = [
+            (Left, Action::PrevImage),
+            (Right, Action::NextImage),
+            (Down, Action::PrevTechnique),
+            (Up, Action::NextTechnique),
+            (Equals, Action::EvUp),
+            (NumpadAdd, Action::EvUp),
+            (Minus, Action::EvDown),
+            (NumpadSubtract, Action::EvDown),
+            (Key0, Action::ResetEv),
+            (Numpad0, Action::ResetEv),
+            (A, Action::ToggleAutoExposure),
+            (E, Action::ToggleAdaptation),
+            (H, Action::ToggleHistogram),
+            (W, Action::CycleWaveform),
+            (V, Action::CycleVectorscope),
+            (C, Action::ToggleChromaticity),
+            (T, Action::ToggleToneCurve),
+            (P, Action::PinToneCurve),
+            (L, Action::CycleLutPlot),
+            (B, Action::ToggleDiffBaseline),
+            (D, Action::CycleDiffView),
+            (F, Action::CycleDebugView),
+            (G, Action::CycleDisplayPrimaries),
+            (F12, Action::Screenshot),
+        ]
+        .into_iter()
+        .map(|(key, action)| (KeyChord::plain(key), action))
+        .collect();
+
+        bindings.insert(
+            KeyChord::new(F12, ModifiersState::SHIFT),
+            Action::ScreenshotWindow,
+        );
+        // `+` is Shift+`=` on most layouts.
+        bindings.insert(KeyChord::new(Equals, ModifiersState::SHIFT), Action::EvUp);
+
+        for (i, key) in BOOKMARK_KEYS.into_iter().enumerate() {
+            let bookmark = i as u8 + 1;
+            bindings.insert(KeyChord::plain(key), Action::RecallBookmark(bookmark));
+            bindings.insert(KeyChord::with_ctrl(key), Action::StoreBookmark(bookmark));
+        }
+
+        Self {
+            bindings,
+            ev_step: 1.0 / 3.0,
+            ev_drag_sensitivity: 0.01,
+        }
+    }
+}
+
+impl KeyBindings {
+    /// The defaults, overridden by `file_path` if it exists.
+    pub fn load(file_path: &Path) -> anyhow::Result<Self> {
+        let mut bindings = Self::default();
+        if !file_path.exists() {
+            return Ok(bindings);
+        }
+
+        let contents = std::fs::read_to_string(file_path)
+            .with_context(|| format!("Reading {:?}", file_path))?;
+
+        for (line_index, line) in contents.lines().enumerate() {
+            bindings
+                .apply_line(line)
+                .with_context(|| format!("{:?}, line {}", file_path, line_index + 1))?;
+        }
+
+        Ok(bindings)
+    }
+
+    /// The action bound to `chord`, or else to its key without modifiers.
+    pub fn action(&self, chord: KeyChord) -> Option<Action> {
+        self.bindings
+            .get(&chord)
+            .or_else(|| self.bindings.get(&KeyChord::plain(chord.key)))
+            .copied()
+    }
+
+    /// `action = key...`, with keys like `Ctrl+Shift+Equals`, or `none` to unbind the action;
+    /// or `ev_step = <stops>`, or `ev_drag_sensitivity = <stops per pixel>`.
+    fn apply_line(&mut self, line: &str) -> anyhow::Result<()> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let (name, value) = line.split_once('=').context("Expected `action = keys`")?;
+        let (name, value) = (name.trim(), value.trim());
+
+        match name {
+            "ev_step" => {
+                self.ev_step = value.parse().context("Invalid EV step")?;
+                anyhow::ensure!(
+                    self.ev_step.is_finite() && self.ev_step > 0.0,
+                    "The EV step must be positive"
+                );
+            }
+            "ev_drag_sensitivity" => {
+                self.ev_drag_sensitivity = value.parse().context("Invalid EV drag sensitivity")?;
+                anyhow::ensure!(
+                    self.ev_drag_sensitivity.is_finite() && self.ev_drag_sensitivity > 0.0,
+                    "The EV drag sensitivity must be positive"
+                );
+            }
+            _ => {
+                let action = parse_action(name)?;
+                let chords = if value == "none" {
+                    Vec::new()
+                } else {
+                    value
+                        .split_whitespace()
+                        .map(parse_chord)
+                        .collect::<anyhow::Result<Vec<_>>>()?
+                };
+
+                self.bindings.retain(|_, bound| *bound != action);
+                for chord in chords {
+                    self.bindings.insert(chord, action);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_action(name: &str) -> anyhow::Result<Action> {
+    ACTION_NAMES
+        .iter()
+        .find(|(_, action_name)| *action_name == name)
+        .map(|(action, _)| *action)
+        .ok_or_else(|| anyhow::anyhow!("Unknown action {:?}", name))
+}
+
+fn parse_chord(s: &str) -> anyhow::Result<KeyChord> {
+    let mut parts: Vec<&str> = s.split('+').collect();
+    let key_name = parts.pop().unwrap_or_default();
+
+    let key = KEY_NAMES
+        .iter()
+        .find(|(_, name)| name.eq_ignore_ascii_case(key_name))
+        .map(|(key, _)| *key)
+        .ok_or_else(|| anyhow::anyhow!("Unknown key {:?}", key_name))?;
+
+    let mut modifiers = ModifiersState::empty();
+    for modifier in parts {
+        modifiers |= match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" => ModifiersState::CTRL,
+            "shift" => ModifiersState::SHIFT,
+            "alt" => ModifiersState::ALT,
+            _ => anyhow::bail!(
+                "Unknown modifier {:?}; expected Ctrl, Shift, or Alt",
+                modifier
+            ),
+        };
+    }
+
+    Ok(KeyChord::new(key, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use VirtualKeyCode::*;
+
+    fn bindings_from(lines: &[&str]) -> anyhow::Result<KeyBindings> {
+        let mut bindings = KeyBindings::default();
+        for line in lines {
+            bindings.apply_line(line)?;
+        }
+        Ok(bindings)
+    }
+
+    #[test]
+    fn parses_modifiers() {
+        assert!(
+            parse_chord("Ctrl+Shift+Key1").unwrap()
+                == KeyChord::new(Key1, ModifiersState::CTRL | ModifiersState::SHIFT)
+        );
+        assert!(parse_chord("alt+f12").unwrap() == KeyChord::new(F12, ModifiersState::ALT));
+        assert!(parse_chord("Equals").unwrap() == KeyChord::plain(Equals));
+        assert!(parse_chord("Meta+A").is_err());
+    }
+
+    #[test]
+    fn none_unbinds_the_action() {
+        let bindings = bindings_from(&["toggle_histogram = none"]).unwrap();
+        assert_eq!(bindings.action(KeyChord::plain(H)), None);
+    }
+
+    #[test]
+    fn listing_an_action_replaces_its_defaults() {
+        let bindings = bindings_from(&["ev_up = Ctrl+Up PageUp"]).unwrap();
+
+        assert_eq!(bindings.action(KeyChord::with_ctrl(Up)), Some(Action::EvUp));
+        assert_eq!(bindings.action(KeyChord::plain(PageUp)), Some(Action::EvUp));
+        assert_eq!(bindings.action(KeyChord::plain(Equals)), None);
+        assert_eq!(bindings.action(KeyChord::plain(NumpadAdd)), None);
+        assert_eq!(
+            bindings.action(KeyChord::plain(Up)),
+            Some(Action::NextTechnique)
+        );
+    }
+
+    #[test]
+    fn rebinds_bookmarks() {
+        let bindings =
+            bindings_from(&["store_bookmark_3 = Alt+F3", "recall_bookmark_3 = F3"]).unwrap();
+
+        assert_eq!(
+            bindings.action(KeyChord::new(F3, ModifiersState::ALT)),
+            Some(Action::StoreBookmark(3))
+        );
+        assert_eq!(
+            bindings.action(KeyChord::plain(F3)),
+            Some(Action::RecallBookmark(3))
+        );
+        assert_eq!(bindings.action(KeyChord::plain(Key3)), None);
+        assert_eq!(bindings.action(KeyChord::with_ctrl(Key3)), None);
+
+        let bindings = bindings_from(&["recall_bookmark_1 = none"]).unwrap();
+        assert_eq!(bindings.action(KeyChord::plain(Key1)), None);
+        assert_eq!(
+            bindings.action(KeyChord::with_ctrl(Key1)),
+            Some(Action::StoreBookmark(1))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_key_without_modifiers() {
+        let bindings = KeyBindings::default();
+
+        assert_eq!(
+            bindings.action(KeyChord::new(Left, ModifiersState::SHIFT)),
+            Some(Action::PrevImage)
+        );
+        assert_eq!(
+            bindings.action(KeyChord::new(F12, ModifiersState::SHIFT)),
+            Some(Action::ScreenshotWindow)
+        );
+        assert_eq!(
+            bindings.action(KeyChord::with_ctrl(Key3)),
+            Some(Action::StoreBookmark(3))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_actions() {
+        assert!(bindings_from(&["ev_up = Ctrl+Plus"]).is_err());
+        assert!(bindings_from(&["ev_upp = Equals"]).is_err());
+        assert!(bindings_from(&["recall_bookmark_0 = Key0"]).is_err());
+        assert!(bindings_from(&["store_bookmark_10 = Ctrl+Key0"]).is_err());
+        assert!(bindings_from(&["ev_up Equals"]).is_err());
+    }
+
+    #[test]
+    fn rejects_non_positive_ev_rates() {
+        for value in ["0", "-0.1", "NaN", "inf"] {
+            assert!(bindings_from(&[&format!("ev_step = {}", value)]).is_err());
+            assert!(bindings_from(&[&format!("ev_drag_sensitivity = {}", value)]).is_err());
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::Context;
+use std::{ffi::c_void, io::Write, path::Path};
+
+use crate::image_loading::ImageRgb32f;
+
+/// How the LUT input in [0, 1] maps to the stimulus fed to the technique.
+///
+/// Written as `linear`, `acescct`, or `log2:<min>:<max>`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LutDomain {
+    /// Input is the linear stimulus.
+    Linear,
+    /// Input is `(log2(stimulus) - min) / (max - min)`, the same as OCIO's `lg2` allocation.
+    Log2 { min: f64, max: f64 },
+    /// Input is the ACEScct encoding of the stimulus.
+    AcesCct,
+}
+
+impl LutDomain {
+    fn decode(&self, t: f64) -> f64 {
+        match *self {
+            LutDomain::Linear => t,
+            LutDomain::Log2 { min, max } => (min + t * (max - min)).exp2(),
+            LutDomain::AcesCct => {
+                if t <= 0.155251141552511 {
+                    (t - 0.0729055341958355) / 10.5402377416545
+                } else {
+                    (t * 17.52 - 9.72).exp2()
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for LutDomain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            LutDomain::Linear => write!(f, "linear"),
+            LutDomain::Log2 { min, max } => write!(f, "log2:{}:{}", min, max),
+            LutDomain::AcesCct => write!(f, "acescct"),
+        }
+    }
+}
+
+impl std::str::FromStr for LutDomain {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let parts: Vec<&str> = s.trim().split(':').collect();
+        match parts.as_slice() {
+            ["linear"] => Ok(Self::Linear),
+            ["acescct"] => Ok(Self::AcesCct),
+            ["log2", min, max] => Ok(Self::Log2 {
+                min: min.parse().context("log2 min")?,
+                max: max.parse().context("log2 max")?,
+            }),
+            _ => anyhow::bail!(
+                "Unknown LUT domain {:?}; expected linear, acescct, or log2:<min>:<max>",
+                s
+            ),
+        }
+    }
+}
+
+/// Comment which records the input domain in `.cube` files, so they can be loaded back.
+pub const DOMAIN_COMMENT_PREFIX: &str = "Input domain: ";
+
+#[derive(Clone, Copy)]
+pub enum LutFileFormat {
+    Cube,
+    Spi3d,
+}
+
+impl LutFileFormat {
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("cube") => Ok(Self::Cube),
+            Some("spi3d") => Ok(Self::Spi3d),
+            _ => anyhow::bail!(
+                "Unsupported LUT format {:?}; expected .cube or .spi3d",
+                path
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct LutBakeDesc {
+    /// Number of lattice points along each axis.
+    pub size: usize,
+    pub domain: LutDomain,
+    pub ev: f64,
+}
+
+impl LutBakeDesc {
+    /// The stimulus at every lattice point, laid out as an image `size` wide and `size * size` tall,
+    /// with red varying fastest, then green, then blue.
+    pub fn lattice_image(&self) -> ImageRgb32f {
+        let n = self.size;
+        let decode = |i: usize| self.domain.decode(i as f64 / (n - 1) as f64) as f32;
+
+        let mut data = Vec::with_capacity(n * n * n * 3);
+        for b in 0..n {
+            for g in 0..n {
+                for r in 0..n {
+                    data.extend_from_slice(&[decode(r), decode(g), decode(b)]);
+                }
+            }
+        }
+
+        ImageRgb32f {
+            size: [n, n * n],
+            data,
+        }
+    }
+}
+
+/// A 3D LUT of display-encoded (sRGB) technique output, with red varying fastest.
+pub struct BakedLut {
+    pub desc: LutBakeDesc,
+    pub title: String,
+    pub data: Vec<[f32; 3]>,
+}
+
+impl BakedLut {
+    /// Reads back the technique applied to `LutBakeDesc::lattice_image` from the bound
+    /// read framebuffer, which must be RGBA32F.
+    pub fn read_from_framebuffer(gl: &gl::Gl, desc: LutBakeDesc, title: String) -> Self {
+        let size = [desc.size, desc.size * desc.size];
+        let mut pixels = vec![0f32; size[0] * size[1] * 4];
+
+        unsafe {
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl.ReadPixels(
+                0,
+                0,
+                size[0] as _,
+                size[1] as _,
+                gl::RGBA,
+                gl::FLOAT,
+                pixels.as_mut_ptr() as *mut c_void,
+            );
+        }
+
+        // Rows come back bottom-up, while the lattice image is top-down.
+        let data = pixels
+            .chunks_exact(size[0] * 4)
+            .rev()
+            .flat_map(|row| row.chunks_exact(4))
+            .map(|px| [px[0], px[1], px[2]].map(|v| linear_to_srgb(v.clamp(0.0, 1.0))))
+            .collect();
+
+        Self { desc, title, data }
+    }
+
+    pub fn write(&self, file_path: &Path) -> anyhow::Result<()> {
+        let format = LutFileFormat::from_path(file_path)?;
+
+        if let Some(parent_dir) = file_path.parent() {
+            std::fs::create_dir_all(parent_dir)?;
+        }
+
+        let mut out = std::io::BufWriter::new(
+            std::fs::File::create(file_path)
+                .with_context(|| format!("Failed to create {:?}", file_path))?,
+        );
+
+        match format {
+            LutFileFormat::Cube => self.write_cube(&mut out),
+            LutFileFormat::Spi3d => self.write_spi3d(&mut out),
+        }
+        .with_context(|| format!("Writing {:?}", file_path))?;
+
+        out.flush()?;
+        Ok(())
+    }
+
+    fn write_cube(&self, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(out, "TITLE \"{}\"", self.title)?;
+        writeln!(out, "# {}{}", DOMAIN_COMMENT_PREFIX, self.desc.domain)?;
+        writeln!(out, "# EV: {}", self.desc.ev)?;
+        writeln!(out, "LUT_3D_SIZE {}", self.desc.size)?;
+        writeln!(out, "DOMAIN_MIN 0.0 0.0 0.0")?;
+        writeln!(out, "DOMAIN_MAX 1.0 1.0 1.0")?;
+
+        for [r, g, b] in &self.data {
+            writeln!(out, "{:.6} {:.6} {:.6}", r, g, b)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_spi3d(&self, out: &mut impl Write) -> std::io::Result<()> {
+        let n = self.desc.size;
+
+        writeln!(out, "SPILUT 1.0")?;
+        writeln!(out, "3 3")?;
+        writeln!(out, "{} {} {}", n, n, n)?;
+
+        // Conventionally listed with blue varying fastest.
+        for r in 0..n {
+            for g in 0..n {
+                for b in 0..n {
+                    let [vr, vg, vb] = self.data[r + n * (g + n * b)];
+                    writeln!(out, "{} {} {} {:.6} {:.6} {:.6}", r, g, b, vr, vg, vb)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
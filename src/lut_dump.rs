@@ -0,0 +1,99 @@
+use anyhow::Context;
+use std::{io::Write, path::Path};
+
+use crate::lut_lib::{LutContents, LutLib};
+
+#[derive(Clone, Copy)]
+pub enum LutDumpFormat {
+    Csv,
+    Exr,
+}
+
+impl std::str::FromStr for LutDumpFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "exr" => Ok(Self::Exr),
+            _ => anyhow::bail!("Unknown LUT dump format {:?}; expected csv or exr", s),
+        }
+    }
+}
+
+/// Reads back every LUT in `lut_lib`, and writes each to `dir` as `<name>.csv` or `<name>.exr`.
+pub fn dump_luts(
+    lut_lib: &LutLib,
+    gl: &gl::Gl,
+    dir: &Path,
+    format: LutDumpFormat,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Creating {:?}", dir))?;
+
+    for lut_index in 0..lut_lib.lut_count() {
+        let (desc, _, stats) = lut_lib.get(lut_index).unwrap();
+        let contents = lut_lib.read_back(lut_index, gl);
+
+        let file_path = match format {
+            LutDumpFormat::Csv => {
+                let file_path = dir.join(format!("{}.csv", desc.name));
+                write_csv(&contents, &file_path)?;
+                file_path
+            }
+            LutDumpFormat::Exr => {
+                let file_path = dir.join(format!("{}.exr", desc.name));
+                write_exr(&contents, &file_path)?;
+                file_path
+            }
+        };
+
+        log::info!(
+            "Saved {:?}; values in [{}, {}], {} NaN or infinite",
+            file_path,
+            stats.min,
+            stats.max,
+            stats.non_finite_count
+        );
+    }
+
+    Ok(())
+}
+
+/// One row per texel: its coordinates, followed by the channel values.
+fn write_csv(contents: &LutContents, file_path: &Path) -> anyhow::Result<()> {
+    let mut out = std::io::BufWriter::new(
+        std::fs::File::create(file_path)
+            .with_context(|| format!("Failed to create {:?}", file_path))?,
+    );
+
+    let channel_names = ["r", "g", "b", "a"];
+    writeln!(
+        out,
+        "x,y,z,{}",
+        channel_names[..contents.channel_count].join(",")
+    )?;
+
+    for ([x, y, z], texel) in contents.texels() {
+        write!(out, "{},{},{}", x, y, z)?;
+        for value in texel {
+            write!(out, ",{}", value)?;
+        }
+        writeln!(out)?;
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+/// The slices of 3D LUTs are stacked vertically. Missing channels are written as zero.
+fn write_exr(contents: &LutContents, file_path: &Path) -> anyhow::Result<()> {
+    let [width, height, depth] = contents.size.map(|dim| dim as usize);
+
+    exr::prelude::write_rgba_file(file_path, width, height * depth, |x, y| {
+        let offset = (y * width + x) * contents.channel_count;
+        let texel = &contents.data[offset..offset + contents.channel_count];
+        let channel = |c: usize| texel.get(c).copied().unwrap_or(0.0);
+        (channel(0), channel(1), channel(2), channel(3))
+    })
+    .with_context(|| format!("Writing {:?}", file_path))
+}
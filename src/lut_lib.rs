@@ -1,6 +1,7 @@
 use anyhow::Context;
 use gl::types::*;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use turbosloth::*;
 
@@ -10,17 +11,174 @@ use crate::texture::Texture;
 #[derive(Hash, PartialEq, Eq, Clone, PartialOrd, Ord)]
 pub struct LutDesc {
     pub width: u32,
+    /// 1 for 1D LUTs.
+    pub height: u32,
+    /// 1 for 1D and 2D LUTs.
+    pub depth: u32,
     pub internal_format: GLenum,
+    /// e.g. `gl::LINEAR`
+    pub filter: GLenum,
+    /// e.g. `gl::CLAMP_TO_EDGE`; applied to all axes.
+    pub wrap: GLenum,
     pub name: String,
     pub shader_path: PathBuf,
+    /// Names of LUTs which the shader samples; they're computed first.
+    pub inputs: Vec<String>,
+}
+
+/// Comment which declares a LUT in its compute shader, e.g.
+/// `// lut: size=64 format=rg32f wrap=repeat`
+///
+/// * `name`: name of the sampler uniform; defaults to the file stem
+/// * `size`: `W`, `WxH`, or `WxHxD`
+/// * `format`: `r32f`, `rg32f`, `rgba32f`, `r16f`, `rg16f`, `rgba16f`, or `rgba8`
+/// * `filter`: `linear` (default) or `nearest`
+/// * `wrap`: `clamp` (default), `repeat`, or `mirror`
+/// * `inputs`: comma-separated names of other LUTs which the shader samples
+const LUT_DECLARATION_PREFIX: &str = "// lut:";
+
+impl LutDesc {
+    /// Parses the declaration in the shader at `shader_path`.
+    /// Returns `None` if the shader doesn't declare a LUT.
+    pub fn from_shader_file(shader_path: &Path) -> anyhow::Result<Option<Self>> {
+        let source = std::fs::read_to_string(shader_path)
+            .with_context(|| format!("Reading {:?}", shader_path))?;
+
+        let declaration = match source
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(LUT_DECLARATION_PREFIX))
+        {
+            Some(declaration) => declaration,
+            None => return Ok(None),
+        };
+
+        let mut desc = LutDesc {
+            width: 0,
+            height: 1,
+            depth: 1,
+            internal_format: 0,
+            filter: gl::LINEAR,
+            wrap: gl::CLAMP_TO_EDGE,
+            name: shader_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            shader_path: shader_path.to_owned(),
+            inputs: Vec::new(),
+        };
+
+        for entry in declaration.split_whitespace() {
+            let (key, value) = entry
+                .split_once('=')
+                .with_context(|| format!("Expected key=value, got {:?}", entry))?;
+
+            match key {
+                "name" => desc.name = value.to_owned(),
+                "size" => {
+                    let dims = value
+                        .split('x')
+                        .map(|dim| dim.parse::<u32>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .with_context(|| format!("Invalid LUT size {:?}", value))?;
+
+                    match dims.as_slice() {
+                        [w] => desc.width = *w,
+                        [w, h] => [desc.width, desc.height] = [*w, *h],
+                        [w, h, d] => [desc.width, desc.height, desc.depth] = [*w, *h, *d],
+                        _ => anyhow::bail!("Invalid LUT size {:?}", value),
+                    }
+                }
+                "format" => {
+                    desc.internal_format = match value {
+                        "r32f" => gl::R32F,
+                        "rg32f" => gl::RG32F,
+                        "rgba32f" => gl::RGBA32F,
+                        "r16f" => gl::R16F,
+                        "rg16f" => gl::RG16F,
+                        "rgba16f" => gl::RGBA16F,
+                        "rgba8" => gl::RGBA8,
+                        _ => anyhow::bail!("Unsupported LUT format {:?}", value),
+                    }
+                }
+                "filter" => {
+                    desc.filter = match value {
+                        "linear" => gl::LINEAR,
+                        "nearest" => gl::NEAREST,
+                        _ => anyhow::bail!("Unsupported LUT filter {:?}", value),
+                    }
+                }
+                "wrap" => {
+                    desc.wrap = match value {
+                        "clamp" => gl::CLAMP_TO_EDGE,
+                        "repeat" => gl::REPEAT,
+                        "mirror" => gl::MIRRORED_REPEAT,
+                        _ => anyhow::bail!("Unsupported LUT wrap mode {:?}", value),
+                    }
+                }
+                "inputs" => {
+                    desc.inputs = value
+                        .split(',')
+                        .filter(|name| !name.is_empty())
+                        .map(str::to_owned)
+                        .collect()
+                }
+                _ => anyhow::bail!("Unknown LUT declaration key {:?}", key),
+            }
+        }
+
+        anyhow::ensure!(
+            desc.width > 0 && desc.height > 0 && desc.depth > 0,
+            "The LUT declaration must specify a non-zero size"
+        );
+        anyhow::ensure!(
+            desc.internal_format != 0,
+            "The LUT declaration must specify a format"
+        );
+
+        Ok(Some(desc))
+    }
 }
 
 pub struct LutHandle(usize);
 
+/// Contents of a LUT texture read back from the GPU.
+pub struct LutContents {
+    pub size: [u32; 3],
+    pub channel_count: usize,
+    /// `channel_count` values per texel, with x varying fastest, then y, then z.
+    pub data: Vec<f32>,
+}
+
+impl LutContents {
+    pub fn texels(&self) -> impl Iterator<Item = ([u32; 3], &[f32])> + '_ {
+        let [width, height, _] = self.size;
+        self.data
+            .chunks_exact(self.channel_count)
+            .enumerate()
+            .map(move |(i, texel)| {
+                let i = i as u32;
+                (
+                    [i % width, (i / width) % height, i / (width * height)],
+                    texel,
+                )
+            })
+    }
+}
+
+/// Summary of a LUT's contents, refreshed whenever it's recomputed.
+#[derive(Clone, Copy, Default)]
+pub struct LutStats {
+    /// Range of the finite values across all channels.
+    pub min: f32,
+    pub max: f32,
+    pub non_finite_count: usize,
+}
+
 struct LutState {
     desc: LutDesc,
     shader: CompiledShader,
     texture: Texture,
+    stats: LutStats,
 }
 
 struct CompiledShader {
@@ -39,7 +197,11 @@ impl CompiledShader {
 
 pub struct LutLib {
     luts: Vec<LutState>,
+    // Indices into `luts`, with inputs before the LUTs which sample them.
+    // LUTs with missing or cyclic inputs are left out. Resolved lazily after LUTs are added.
+    evaluation_order: Option<Vec<usize>>,
     lazy_cache: Arc<LazyCache>,
+    uniforms: UniformCache,
 }
 
 pub enum AnyLutsChanged {
@@ -51,13 +213,21 @@ impl LutLib {
     pub fn new(lazy_cache: &Arc<LazyCache>) -> Self {
         Self {
             luts: Default::default(),
+            evaluation_order: None,
             lazy_cache: lazy_cache.clone(),
+            uniforms: Default::default(),
         }
     }
 
     pub fn add_lut(&mut self, lut_desc: LutDesc, gl: &gl::Gl) -> LutHandle {
         let handle = LutHandle(self.luts.len());
-        let texture = Texture::new_1d(gl, lut_desc.width, lut_desc.internal_format);
+        let texture = Texture::new_storage(
+            gl,
+            [lut_desc.width, lut_desc.height, lut_desc.depth],
+            lut_desc.internal_format,
+            lut_desc.filter,
+            lut_desc.wrap,
+        );
         assert_eq!(texture.internal_format, lut_desc.internal_format);
 
         self.luts.push(LutState {
@@ -68,15 +238,48 @@ impl LutLib {
                 .into_lazy(),
             ),
             texture,
+            stats: Default::default(),
             desc: lut_desc,
         });
+        self.evaluation_order = None;
         handle
     }
 
+    /// Adds every LUT declared by a compute shader in `folder`.
+    /// See `LUT_DECLARATION_PREFIX` for the declaration syntax.
+    pub fn add_luts_from_folder(
+        &mut self,
+        folder: impl AsRef<Path>,
+        gl: &gl::Gl,
+    ) -> anyhow::Result<()> {
+        let folder = folder.as_ref();
+        let mut shader_paths: Vec<PathBuf> = std::fs::read_dir(folder)
+            .with_context(|| format!("Reading {:?}", folder))?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.is_file() && path.extension() == Some(std::ffi::OsStr::new("glsl")))
+                    .then_some(path)
+            })
+            .collect();
+        shader_paths.sort();
+
+        for shader_path in shader_paths {
+            match LutDesc::from_shader_file(&shader_path) {
+                Ok(Some(desc)) => {
+                    self.add_lut(desc, gl);
+                }
+                Ok(None) => log::warn!("{:?} doesn't declare a LUT; skipping", shader_path),
+                Err(err) => log::error!("Invalid LUT declaration in {:?}: {:?}", shader_path, err),
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn compile_all(&mut self, gl: &gl::Gl) -> AnyLutsChanged {
-        let mut any_shaders_changed = AnyLutsChanged::No;
+        let mut recomputed = vec![false; self.luts.len()];
 
-        for lut in &mut self.luts {
+        for (lut, recomputed) in self.luts.iter_mut().zip(&mut recomputed) {
             if !lut.shader.preprocessed.is_up_to_date() {
                 let handle: anyhow::Result<u32> =
                     smol::block_on(lut.shader.preprocessed.eval(&self.lazy_cache))
@@ -88,40 +291,215 @@ impl LutLib {
                 match handle {
                     Ok(handle) => {
                         log::info!("Shader compiled.");
-                        lut.shader.gl_handle = Some(handle);
-                        any_shaders_changed = AnyLutsChanged::Yes;
-
-                        Self::compute_lut(lut, gl);
+                        if let Some(old_handle) = lut.shader.gl_handle.replace(handle) {
+                            self.uniforms.forget(old_handle);
+                        }
+                        *recomputed = true;
                     }
                     Err(err) => log::error!("Shader failed to compile: {:?}", err),
                 }
             }
         }
 
-        any_shaders_changed
+        if !recomputed.contains(&true) {
+            return AnyLutsChanged::No;
+        }
+
+        let luts = &self.luts;
+        let evaluation_order = self
+            .evaluation_order
+            .get_or_insert_with(|| Self::resolve_evaluation_order(luts));
+
+        // Inputs come first, so changes propagate downstream in a single pass.
+        for &lut_index in evaluation_order.iter() {
+            let lut = &self.luts[lut_index];
+            let input_recomputed = lut.desc.inputs.iter().any(|input| {
+                self.luts
+                    .iter()
+                    .position(|other| &other.desc.name == input)
+                    .is_some_and(|input_index| recomputed[input_index])
+            });
+
+            if (recomputed[lut_index] || input_recomputed) && lut.shader.gl_handle.is_some() {
+                Self::compute_lut(&self.luts, &self.uniforms, lut_index, gl);
+                recomputed[lut_index] = true;
+
+                let stats = Self::validate_lut(&self.luts[lut_index], gl);
+                self.luts[lut_index].stats = stats;
+            }
+        }
+
+        AnyLutsChanged::Yes
+    }
+
+    fn resolve_evaluation_order(luts: &[LutState]) -> Vec<usize> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Unvisited,
+            Visiting,
+            Done,
+            Failed,
+        }
+
+        fn visit(
+            lut_index: usize,
+            luts: &[LutState],
+            by_name: &HashMap<&str, usize>,
+            marks: &mut [Mark],
+            order: &mut Vec<usize>,
+        ) -> bool {
+            match marks[lut_index] {
+                Mark::Done => return true,
+                Mark::Failed => return false,
+                Mark::Visiting => {
+                    log::error!("LUT {:?} depends on itself", luts[lut_index].desc.name);
+                    return false;
+                }
+                Mark::Unvisited => {}
+            }
+
+            marks[lut_index] = Mark::Visiting;
+
+            let mut inputs_ok = true;
+            for input in &luts[lut_index].desc.inputs {
+                if let Some(&input_index) = by_name.get(input.as_str()) {
+                    inputs_ok &= visit(input_index, luts, by_name, marks, order);
+                } else {
+                    log::error!(
+                        "LUT {:?} depends on unknown LUT {:?}",
+                        luts[lut_index].desc.name,
+                        input
+                    );
+                    inputs_ok = false;
+                }
+            }
+
+            if inputs_ok {
+                marks[lut_index] = Mark::Done;
+                order.push(lut_index);
+            } else {
+                marks[lut_index] = Mark::Failed;
+            }
+
+            inputs_ok
+        }
+
+        let by_name: HashMap<&str, usize> = luts
+            .iter()
+            .enumerate()
+            .map(|(lut_index, lut)| (lut.desc.name.as_str(), lut_index))
+            .collect();
+
+        let mut marks = vec![Mark::Unvisited; luts.len()];
+        let mut order = Vec::with_capacity(luts.len());
+
+        for lut_index in 0..luts.len() {
+            visit(lut_index, luts, &by_name, &mut marks, &mut order);
+        }
+
+        order
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (&LutDesc, &Texture)> {
         self.luts.iter().map(|lut| (&lut.desc, &lut.texture))
     }
 
-    fn compute_lut(lut: &mut LutState, gl: &gl::Gl) {
+    pub fn lut_count(&self) -> usize {
+        self.luts.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<(&LutDesc, &Texture, LutStats)> {
+        self.luts
+            .get(index)
+            .map(|lut| (&lut.desc, &lut.texture, lut.stats))
+    }
+
+    /// Reads back the LUT at `index`, stalling until the GPU is done computing it.
+    pub fn read_back(&self, index: usize, gl: &gl::Gl) -> LutContents {
+        Self::read_back_lut(&self.luts[index], gl)
+    }
+
+    fn read_back_lut(lut: &LutState, gl: &gl::Gl) -> LutContents {
+        let (format, channel_count) = match lut.desc.internal_format {
+            gl::R32F | gl::R16F => (gl::RED, 1),
+            gl::RG32F | gl::RG16F => (gl::RG, 2),
+            _ => (gl::RGBA, 4),
+        };
+
+        let size = [lut.desc.width, lut.desc.height, lut.desc.depth];
+        let mut data = vec![0f32; size.iter().product::<u32>() as usize * channel_count];
+
+        unsafe {
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl.GetTextureImage(
+                lut.texture.id,
+                0,
+                format,
+                gl::FLOAT,
+                (data.len() * std::mem::size_of::<f32>()) as _,
+                data.as_mut_ptr() as *mut std::ffi::c_void,
+            );
+        }
+
+        LutContents {
+            size,
+            channel_count,
+            data,
+        }
+    }
+
+    fn validate_lut(lut: &LutState, gl: &gl::Gl) -> LutStats {
+        let contents = Self::read_back_lut(lut, gl);
+
+        let mut stats = LutStats {
+            min: f32::MAX,
+            max: f32::MIN,
+            non_finite_count: 0,
+        };
+
+        for &value in &contents.data {
+            if value.is_finite() {
+                stats.min = stats.min.min(value);
+                stats.max = stats.max.max(value);
+            } else {
+                stats.non_finite_count += 1;
+            }
+        }
+
+        if stats.non_finite_count > 0 {
+            log::warn!(
+                "LUT {:?} contains {} NaN or infinite values",
+                lut.desc.name,
+                stats.non_finite_count
+            );
+        }
+
+        if stats.min > stats.max {
+            stats.min = 0.0;
+            stats.max = 0.0;
+        }
+
+        stats
+    }
+
+    fn compute_lut(luts: &[LutState], uniforms: &UniformCache, lut_index: usize, gl: &gl::Gl) {
+        let lut = &luts[lut_index];
         let shader_program = lut.shader.gl_handle.unwrap();
 
         unsafe {
             gl.UseProgram(shader_program);
 
             {
-                let loc =
-                    gl.GetUniformLocation(shader_program, "output_image\0".as_ptr() as *const i8);
+                let loc = uniforms.location(gl, shader_program, "output_image");
                 if loc != -1 {
                     let img_unit = 0;
                     gl.Uniform1i(loc, img_unit);
+                    // Layered, so that all slices of 3D LUTs are bound.
                     gl.BindImageTexture(
                         img_unit as _,
                         lut.texture.id,
                         0,
-                        gl::FALSE,
+                        gl::TRUE,
                         0,
                         gl::READ_WRITE,
                         lut.texture.internal_format,
@@ -129,19 +507,31 @@ impl LutLib {
                 }
             }
 
-            let mut work_group_size: [i32; 3] = [0, 0, 0];
-            gl.GetProgramiv(
-                shader_program,
-                gl::COMPUTE_WORK_GROUP_SIZE,
-                &mut work_group_size[0],
-            );
-
-            fn div_up(a: u32, b: u32) -> u32 {
-                (a + b - 1) / b
+            // Inputs are bound as samplers named after them.
+            let mut tex_unit = 0;
+            for input in luts
+                .iter()
+                .filter(|other| lut.desc.inputs.contains(&other.desc.name))
+            {
+                let loc = uniforms.location(gl, shader_program, &input.desc.name);
+                if loc != -1 {
+                    gl.ActiveTexture(gl::TEXTURE0 + tex_unit as GLenum);
+                    gl.BindTexture(input.texture.ty, input.texture.id);
+                    gl.Uniform1i(loc, tex_unit);
+                    tex_unit += 1;
+                }
             }
 
-            gl.DispatchCompute(div_up(lut.desc.width, work_group_size[0] as u32), 1, 1);
+            dispatch_compute(
+                gl,
+                shader_program,
+                [lut.desc.width, lut.desc.height, lut.desc.depth],
+            );
             gl.UseProgram(0);
+            gl.ActiveTexture(gl::TEXTURE0);
+
+            // Make the results visible to LUTs and techniques which sample this one.
+            gl.MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT | gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
         }
     }
 }
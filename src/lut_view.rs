@@ -0,0 +1,72 @@
+use crate::{
+    lut_lib::LutLib,
+    overlay::{draw_overlay, OverlayRect},
+    shader::ShaderKey,
+    shader_lib::ShaderLib,
+};
+
+pub const LUT_VIEW_OVERLAY_SIZE: [i32; 2] = [320, 320];
+
+/// Plots the channels of a LUT from `LutLib`: curves for 1D LUTs, images for 2D ones,
+/// and the middle slice of 3D ones. Values are normalized to the LUT's range.
+pub struct LutView {
+    draw_shader: ShaderKey,
+}
+
+impl LutView {
+    pub fn new(shader_lib: &mut ShaderLib) -> Self {
+        Self {
+            draw_shader: shader_lib.add_shader("shaders/scope/lut_draw.glsl"),
+        }
+    }
+
+    pub fn draw(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        lut_lib: &LutLib,
+        lut_index: usize,
+        rect: OverlayRect,
+    ) {
+        let (desc, texture, stats) = match lut_lib.get(lut_index) {
+            Some(lut) => lut,
+            None => return,
+        };
+
+        // Also the index of the sampler below.
+        let dims = match texture.ty {
+            gl::TEXTURE_3D => 3,
+            gl::TEXTURE_2D => 2,
+            _ => 1,
+        };
+
+        let channel_count = match desc.internal_format {
+            gl::R32F | gl::R16F => 1,
+            gl::RG32F | gl::RG16F => 2,
+            _ => 4,
+        };
+
+        if let Some(shader_program) = shader_lib.get_shader_gl_handle(&self.draw_shader) {
+            draw_overlay(gl, shader_program, rect, |shader_program| unsafe {
+                // Samplers of different types must not share texture units.
+                for (unit, name) in [(0, "lut_1d"), (1, "lut_2d"), (2, "lut_3d")] {
+                    let loc = shader_lib.uniform_location(gl, shader_program, name);
+                    gl.Uniform1i(loc, unit);
+                }
+
+                gl.ActiveTexture(gl::TEXTURE0 + dims as u32 - 1);
+                gl.BindTexture(texture.ty, texture.id);
+                gl.ActiveTexture(gl::TEXTURE0);
+
+                let loc = shader_lib.uniform_location(gl, shader_program, "lut_dims");
+                gl.Uniform1i(loc, dims);
+
+                let loc = shader_lib.uniform_location(gl, shader_program, "lut_channel_count");
+                gl.Uniform1i(loc, channel_count);
+
+                let loc = shader_lib.uniform_location(gl, shader_program, "lut_value_range");
+                gl.Uniform2f(loc, stats.min, stats.max);
+            });
+        }
+    }
+}
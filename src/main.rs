@@ -1,22 +1,57 @@
+mod adaptation;
 mod app_state;
+mod buffer;
+mod chromaticity;
+mod debug_view;
+mod external_lut;
 mod fbo;
 mod file;
+mod frame_constants;
+mod histogram;
+mod hue_analysis;
+mod icc_profile;
+mod image_diff;
 mod image_loading;
 mod image_pool;
+mod image_saving;
+mod image_stats;
+mod key_bindings;
+mod lut_bake;
+mod lut_dump;
 mod lut_lib;
+mod lut_view;
+mod ocio;
+mod output_target;
+mod overlay;
+mod pass_graph;
+mod provenance;
+mod screenshot;
+mod session;
 mod setup;
 mod shader;
 mod shader_lib;
 mod texture;
+mod tone_curve;
+mod vectorscope;
+mod waveform;
 
 use std::path::PathBuf;
 
+use adaptation::AdaptationRates;
 use anyhow::Context;
 use app_state::*;
+use frame_constants::TECHNIQUE_PARAM_COUNT;
 use glutin::event::{Event, WindowEvent};
 use glutin::event_loop::{ControlFlow, EventLoop};
 use glutin::window::WindowBuilder;
 use glutin::ContextBuilder;
+use image_saving::CaptureFormat;
+use key_bindings::{KeyBindings, DEFAULT_KEY_BINDINGS_FILE};
+use lut_bake::{LutBakeDesc, LutDomain};
+use lut_dump::LutDumpFormat;
+use output_target::{DisplayPrimaries, OutputEncoding, OutputTarget};
+use screenshot::{ScreenshotNaming, DEFAULT_SCREENSHOT_DIR, DEFAULT_SCREENSHOT_NAME};
+use session::{Session, DEFAULT_SESSION_FILE};
 
 use structopt::StructOpt;
 
@@ -30,6 +65,46 @@ struct Opt {
     )]
     input: PathBuf,
 
+    /// Pick the initial EV of each image based on its luminance
+    #[structopt(long)]
+    auto_exposure: bool,
+
+    /// Input domain of .cube techniques which don't specify one: linear, acescct, or log2:<min>:<max>
+    #[structopt(long, default_value = "linear")]
+    lut_domain: LutDomain,
+
+    /// Primaries of the display: bt709, p3, or bt2020. Batch output defaults to bt2020 for pq and hlg
+    #[structopt(long)]
+    display_primaries: Option<DisplayPrimaries>,
+
+    /// File the viewer restores its state and bookmarks from, and saves them to on exit
+    #[structopt(long, parse(from_os_str), default_value = DEFAULT_SESSION_FILE)]
+    session: PathBuf,
+
+    /// File overriding the default key bindings and EV step
+    #[structopt(long, parse(from_os_str), default_value = DEFAULT_KEY_BINDINGS_FILE)]
+    key_bindings: PathBuf,
+
+    /// Folder for screenshots
+    #[structopt(long, parse(from_os_str), default_value = DEFAULT_SCREENSHOT_DIR)]
+    screenshot_dir: PathBuf,
+
+    /// Screenshot file name, without the extension; {image}, {shader}, {ev} and {timestamp} are replaced
+    #[structopt(long, default_value = DEFAULT_SCREENSHOT_NAME)]
+    screenshot_name: String,
+
+    /// Comma-separated values of `frame_params`, readable by every technique; up to 8
+    #[structopt(long, use_delimiter = true)]
+    params: Vec<f32>,
+
+    /// Time constant of eye adaptation towards brighter frames, in seconds (0.4 by default)
+    #[structopt(long)]
+    adapt_to_light: Option<f64>,
+
+    /// Time constant of eye adaptation towards darker frames, in seconds (4 by default)
+    #[structopt(long)]
+    adapt_to_dark: Option<f64>,
+
     #[structopt(subcommand)]
     cmd: Option<Command>,
 }
@@ -52,6 +127,132 @@ struct BatchCmd {
     /// EV step
     #[structopt(long, default_value = "1.0")]
     ev_step: f64,
+
+    /// Display to encode for: srgb, pq[:<peak nits>], hlg[:<peak nits>], or scrgb[:<peak nits>]
+    #[structopt(long, default_value = "srgb")]
+    output_target: OutputEncoding,
+
+    /// jpg (sRGB only), png (16-bit), or exr (float)
+    #[structopt(long, default_value = "jpg")]
+    format: CaptureFormat,
+
+    /// Render the EV sweep of each image as a sequence at this many frames per second,
+    /// with simulated eye adaptation
+    #[structopt(long)]
+    adaptation_frame_rate: Option<f64>,
+}
+
+#[derive(StructOpt)]
+#[structopt(settings = &[structopt::clap::AppSettings::AllowNegativeNumbers])]
+struct BakeCmd {
+    /// Name of the shader, without the path or file extension, e.g. "linear"
+    #[structopt(long)]
+    shader: String,
+
+    /// Output file; .cube or .spi3d
+    #[structopt(long, parse(from_os_str))]
+    output: PathBuf,
+
+    /// Number of lattice points along each axis
+    #[structopt(long, default_value = "33")]
+    size: usize,
+
+    /// Exposure applied to the stimulus before the technique
+    #[structopt(long, default_value = "0.0")]
+    ev: f64,
+
+    /// Use a linear input domain instead of the log2 one
+    #[structopt(long)]
+    linear: bool,
+
+    /// Log2 of the stimulus at LUT input 0
+    #[structopt(long, default_value = "-10.0")]
+    log2_min: f64,
+
+    /// Log2 of the stimulus at LUT input 1
+    #[structopt(long, default_value = "6.0")]
+    log2_max: f64,
+}
+
+#[derive(StructOpt)]
+#[structopt(settings = &[structopt::clap::AppSettings::AllowNegativeNumbers])]
+struct ExportOcioCmd {
+    /// Techniques to export; all of them if omitted
+    #[structopt(long = "shader")]
+    shaders: Vec<String>,
+
+    /// Folder for config.ocio and its LUTs
+    #[structopt(long, parse(from_os_str), default_value = "ocio")]
+    output: PathBuf,
+
+    /// Number of lattice points along each axis
+    #[structopt(long, default_value = "33")]
+    size: usize,
+
+    /// Log2 of the stimulus at LUT input 0
+    #[structopt(long, default_value = "-10.0")]
+    log2_min: f64,
+
+    /// Log2 of the stimulus at LUT input 1
+    #[structopt(long, default_value = "6.0")]
+    log2_max: f64,
+}
+
+#[derive(StructOpt)]
+struct DumpLutsCmd {
+    /// Folder to write the LUTs to
+    #[structopt(long, parse(from_os_str), default_value = "lut_dump")]
+    output: PathBuf,
+
+    /// csv or exr
+    #[structopt(long, default_value = "csv")]
+    format: LutDumpFormat,
+}
+
+#[derive(StructOpt)]
+struct AnalyzeCmd {
+    /// Techniques to analyze; all of them if omitted
+    #[structopt(long = "shader")]
+    shaders: Vec<String>,
+
+    /// Folder to write the reports to
+    #[structopt(long, parse(from_os_str), default_value = "analysis")]
+    output: PathBuf,
+}
+
+#[derive(StructOpt)]
+#[structopt(settings = &[structopt::clap::AppSettings::AllowNegativeNumbers])]
+struct DiffCmd {
+    /// Name of the shader to evaluate
+    #[structopt(long)]
+    shader: String,
+
+    /// Name of the shader to compare against
+    #[structopt(long, required_unless = "reference", conflicts_with = "reference")]
+    baseline: Option<String>,
+
+    /// Display-linear .exr or .hdr image to compare against, the same size as the input
+    #[structopt(long, parse(from_os_str))]
+    reference: Option<PathBuf>,
+
+    /// Exposure applied to the stimulus before the techniques
+    #[structopt(long, default_value = "0.0")]
+    ev: f64,
+
+    /// Folder to write the heatmaps and stats to
+    #[structopt(long, parse(from_os_str), default_value = "diff")]
+    output: PathBuf,
+}
+
+#[derive(StructOpt)]
+struct BookmarksCmd {
+    /// Folder to write the captures to
+    #[structopt(long, parse(from_os_str), default_value = "bookmarks")]
+    output: PathBuf,
+
+    /// jpg, png (16-bit), or exr (float)
+    #[structopt(long, default_value = "jpg")]
+    format: CaptureFormat,
 }
 
 #[derive(StructOpt)]
@@ -60,6 +261,18 @@ enum Command {
     View,
     /// Batch-processes images
     Batch(BatchCmd),
+    /// Bakes a technique into a 3D LUT
+    Bake(BakeCmd),
+    /// Bakes techniques into an OpenColorIO config
+    ExportOcio(ExportOcioCmd),
+    /// Writes the contents of the precomputed LUTs
+    DumpLuts(DumpLutsCmd),
+    /// Measures how techniques shift hues across exposures
+    Analyze(AnalyzeCmd),
+    /// Measures the ΔE between a technique and another one, or a reference image
+    Diff(DiffCmd),
+    /// Captures every bookmark of the session
+    Bookmarks(BookmarksCmd),
 }
 
 fn main() -> anyhow::Result<()> {
@@ -84,27 +297,157 @@ fn main() -> anyhow::Result<()> {
     let gl = gl::Gl::load_with(|symbol| windowed_context.get_proc_address(symbol) as *const _);
     setup::setup_basic_gl_state(&gl);
 
-    let mut state = AppState::new(opt.input, &gl)?;
+    let mut state = AppState::new(opt.input, opt.lut_domain, &gl)?;
+
+    let session = Session::load(&opt.session);
+    if matches!(opt.cmd, Some(Command::View) | None) {
+        match &session {
+            Ok(session) => {
+                state.restore_session(session);
+                state.session_file = Some(opt.session.clone());
+            }
+            // Don't overwrite the file on exit, so that hand edits can be fixed up instead of lost.
+            Err(err) => log::error!(
+                "Failed to load the session; it won't be saved on exit: {:?}",
+                err
+            ),
+        }
+    }
+
+    state.key_bindings = KeyBindings::load(&opt.key_bindings).unwrap_or_else(|err| {
+        log::error!("Failed to load the key bindings: {:?}", err);
+        KeyBindings::default()
+    });
+
+    state.screenshot_naming = ScreenshotNaming::new(opt.screenshot_dir, opt.screenshot_name)?;
+
+    anyhow::ensure!(
+        opt.params.len() <= TECHNIQUE_PARAM_COUNT,
+        "At most {} --params are supported",
+        TECHNIQUE_PARAM_COUNT
+    );
+    state.technique_params[..opt.params.len()].copy_from_slice(&opt.params);
+
+    let default_rates = AdaptationRates::default();
+    state.set_adaptation_rates(AdaptationRates {
+        to_light: opt.adapt_to_light.unwrap_or(default_rates.to_light),
+        to_dark: opt.adapt_to_dark.unwrap_or(default_rates.to_dark),
+    });
+
+    // The command line overrides the session.
+    if opt.auto_exposure {
+        state.set_auto_exposure(true);
+    }
+    if let Some(display_primaries) = opt.display_primaries {
+        state.display_primaries = display_primaries;
+    }
     let mut exit_upon_batch_completion = false;
 
-    if let Some(Command::Batch(BatchCmd {
-        shader,
-        ev_min,
-        ev_max,
-        ev_step,
-    })) = opt.cmd
-    {
-        state
-            .request_batch(ev_min, ev_max, ev_step, &shader)
-            .context("state.request_batch")?;
-        exit_upon_batch_completion = true;
+    match opt.cmd {
+        Some(Command::Batch(BatchCmd {
+            shader,
+            ev_min,
+            ev_max,
+            ev_step,
+            output_target,
+            format,
+            adaptation_frame_rate,
+        })) => {
+            let output_target = OutputTarget::new(output_target, opt.display_primaries)?;
+            state
+                .request_batch(
+                    ev_min,
+                    ev_max,
+                    ev_step,
+                    &shader,
+                    output_target,
+                    format,
+                    adaptation_frame_rate,
+                )
+                .context("state.request_batch")?;
+            exit_upon_batch_completion = true;
+        }
+        Some(Command::Bake(BakeCmd {
+            shader,
+            output,
+            size,
+            ev,
+            linear,
+            log2_min,
+            log2_max,
+        })) => {
+            let domain = if linear {
+                LutDomain::Linear
+            } else {
+                LutDomain::Log2 {
+                    min: log2_min,
+                    max: log2_max,
+                }
+            };
+
+            state
+                .request_lut_bake(LutBakeDesc { size, domain, ev }, &shader, output)
+                .context("state.request_lut_bake")?;
+            exit_upon_batch_completion = true;
+        }
+        Some(Command::ExportOcio(ExportOcioCmd {
+            shaders,
+            output,
+            size,
+            log2_min,
+            log2_max,
+        })) => {
+            state
+                .request_ocio_export(&shaders, &output, size, log2_min, log2_max)
+                .context("state.request_ocio_export")?;
+            exit_upon_batch_completion = true;
+        }
+        Some(Command::DumpLuts(DumpLutsCmd { output, format })) => {
+            state.request_lut_dump(output, format);
+            exit_upon_batch_completion = true;
+        }
+        Some(Command::Analyze(AnalyzeCmd { shaders, output })) => {
+            state
+                .request_analysis(&shaders, &output)
+                .context("state.request_analysis")?;
+            exit_upon_batch_completion = true;
+        }
+        Some(Command::Diff(DiffCmd {
+            shader,
+            baseline,
+            reference,
+            ev,
+            output,
+        })) => {
+            state
+                .request_diff(&shader, baseline.as_deref(), reference, ev, output)
+                .context("state.request_diff")?;
+            exit_upon_batch_completion = true;
+        }
+        Some(Command::Bookmarks(BookmarksCmd { output, format })) => {
+            let session = session.context("Failed to load the session")?;
+            // As seen in the viewer, where `--display-primaries` takes precedence too.
+            if opt.display_primaries.is_none() {
+                if let Some(display_primaries) = session.display_primaries {
+                    state.display_primaries = display_primaries;
+                }
+            }
+            state
+                .request_bookmark_captures(&session.bookmarks, &output, format)
+                .context("state.request_bookmark_captures")?;
+            exit_upon_batch_completion = true;
+        }
+        Some(Command::View) | None => {}
     }
 
     el.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
 
         match event {
-            Event::LoopDestroyed => {}
+            Event::LoopDestroyed => {
+                state.save_session();
+                state.destroy_render_targets(&gl);
+            }
             Event::WindowEvent { event, .. } => match event {
                 WindowEvent::Resized(physical_size) => windowed_context.resize(physical_size),
                 WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
@@ -120,12 +463,17 @@ fn main() -> anyhow::Result<()> {
                 }
 
                 windowed_context.window().set_title(&format!(
-                    "{} | EV {:2.2} | {}",
+                    "{} | EV {:2.2} | {}{}",
                     state
                         .current_image_name()
                         .unwrap_or_else(|| "notorious6".to_owned()),
                     state.ev,
-                    state.current_shader()
+                    state.current_shader(),
+                    state
+                        .debug_view
+                        .name()
+                        .map(|name| format!(" | {}", name))
+                        .unwrap_or_default()
                 ));
 
                 match state.process_batched_requests(&gl) {
@@ -136,7 +484,7 @@ fn main() -> anyhow::Result<()> {
                     }
                 }
 
-                if state.pending_image_capture.is_empty() && exit_upon_batch_completion {
+                if !state.has_pending_requests() && exit_upon_batch_completion {
                     *control_flow = ControlFlow::Exit;
                 }
             }
@@ -0,0 +1,152 @@
+use anyhow::Context;
+use std::path::Path;
+
+/// Folder, relative to the config, which holds the baked LUTs.
+pub const OCIO_LUT_FOLDER: &str = "luts";
+
+const REFERENCE_SPACE: &str = "lin_bt709";
+const DISPLAY_SPACE: &str = "srgb_display";
+
+const SRGB_DECODE: &str = "!<ExponentWithLinearTransform> {gamma: 2.4, offset: 0.055}";
+const SRGB_ENCODE: &str =
+    "!<ExponentWithLinearTransform> {gamma: 2.4, offset: 0.055, direction: inverse}";
+
+/// Name of the LUT file baked for a technique, so that it can be referenced from the config.
+pub fn ocio_lut_file_name(technique_name: &str) -> String {
+    let stem: String = technique_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.spi3d", stem)
+}
+
+/// Writes a self-contained OCIO v2 `config.ocio` into `dir`.
+///
+/// The reference space is the scene-linear BT.709 stimulus which the techniques consume.
+/// Each technique becomes a look, which applies its LUT over a `lg2` allocation of
+/// `[log2_min, log2_max]`, and a view of the sRGB display which uses that look.
+pub fn write_ocio_config(
+    dir: &Path,
+    technique_names: &[String],
+    log2_min: f64,
+    log2_max: f64,
+) -> anyhow::Result<()> {
+    let mut config = String::new();
+    let mut w = |line: String| {
+        config.push_str(&line);
+        config.push('\n');
+    };
+
+    w("ocio_profile_version: 2".into());
+    w("".into());
+    w("# Generated by notorious6 export-ocio".into());
+    w("environment: {}".into());
+    w(format!("search_path: {}", OCIO_LUT_FOLDER));
+    w("strictparsing: true".into());
+    w("luma: [0.2126, 0.7152, 0.0722]".into());
+    w("".into());
+    w("roles:".into());
+    for role in ["reference", "scene_linear", "default", "rendering"] {
+        w(format!("  {}: {}", role, REFERENCE_SPACE));
+    }
+    w(format!("  color_picking: {}", DISPLAY_SPACE));
+    w("  data: Raw".into());
+    w("".into());
+    w("file_rules:".into());
+    w("  - !<Rule> {name: Default, colorspace: default}".into());
+    w("".into());
+
+    w("displays:".into());
+    w("  sRGB:".into());
+    for name in technique_names {
+        w(format!(
+            "    - !<View> {{name: {:?}, colorspace: {}, looks: {:?}}}",
+            name, DISPLAY_SPACE, name
+        ));
+    }
+    w(format!(
+        "    - !<View> {{name: Untransformed, colorspace: {}}}",
+        DISPLAY_SPACE
+    ));
+    w("    - !<View> {name: Raw, colorspace: Raw}".into());
+    w("".into());
+    w("active_displays: [sRGB]".into());
+    w("active_views: []".into());
+    w("".into());
+
+    w("looks:".into());
+    for name in technique_names {
+        w("  - !<Look>".into());
+        w(format!("    name: {:?}", name));
+        w(format!("    process_space: {}", REFERENCE_SPACE));
+        w("    transform: !<GroupTransform>".into());
+        w("      children:".into());
+        w(format!(
+            "        - !<AllocationTransform> {{allocation: lg2, vars: [{}, {}]}}",
+            log2_min, log2_max
+        ));
+        w(format!(
+            "        - !<FileTransform> {{src: {:?}, interpolation: tetrahedral}}",
+            ocio_lut_file_name(name)
+        ));
+        // The LUT outputs sRGB code values; decode them so that the view can re-encode.
+        w(format!("        - {}", SRGB_DECODE));
+    }
+    w("".into());
+
+    w("colorspaces:".into());
+    let mut colorspace = |name: &str, family: &str, description: &str, extra: &[String]| {
+        w("  - !<ColorSpace>".into());
+        w(format!("    name: {}", name));
+        w(format!("    family: {}", family));
+        w("    bitdepth: 32f".into());
+        w(format!("    description: {}", description));
+        for line in extra {
+            w(format!("    {}", line));
+        }
+        w("".into());
+    };
+
+    colorspace(
+        REFERENCE_SPACE,
+        "Scene-linear",
+        "Scene-linear stimulus with BT.709 primaries, as consumed by the techniques",
+        &[
+            "isdata: false".into(),
+            "allocation: lg2".into(),
+            format!("allocationvars: [{}, {}]", log2_min, log2_max),
+        ],
+    );
+    colorspace(
+        DISPLAY_SPACE,
+        "Display",
+        "sRGB-encoded display output",
+        &[
+            "isdata: false".into(),
+            "from_scene_reference: !<GroupTransform>".into(),
+            "  children:".into(),
+            "    - !<RangeTransform> {min_in_value: 0, min_out_value: 0, max_in_value: 1, max_out_value: 1}".into(),
+            format!("    - {}", SRGB_ENCODE),
+        ],
+    );
+    colorspace(
+        "Raw",
+        "Utility",
+        "Data which should not be color-managed",
+        &["isdata: true".into()],
+    );
+
+    std::fs::create_dir_all(dir).with_context(|| format!("Creating {:?}", dir))?;
+    let config_path = dir.join("config.ocio");
+    std::fs::write(&config_path, config).with_context(|| format!("Writing {:?}", config_path))?;
+
+    log::info!("Saved {:?}", config_path);
+
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use std::{fmt, str::FromStr};
+
+use crate::shader_lib::ShaderLib;
+
+/// Transfer function which the technique output is encoded with, by `encode_output_target`
+/// in `shaders/inc/output_target.glsl`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutputEncoding {
+    /// SDR; sRGB-encoded by the framebuffer.
+    Srgb,
+    /// BT.2100 PQ.
+    Pq { peak_nits: f32 },
+    /// BT.2100 HLG, for a display of the given nominal peak.
+    Hlg { peak_nits: f32 },
+    /// Linear, with 1.0 at 80 nits. Always with BT.709 primaries.
+    ScRgb { peak_nits: f32 },
+}
+
+/// Primaries of the display; the technique output is converted to them from BT.709.
+// Must match the `DISPLAY_PRIMARIES_*` defines in `shaders/inc/output_target.glsl`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DisplayPrimaries {
+    Bt709 = 0,
+    /// P3 with a D65 white point, as in Display P3 and P3-D65 mastering.
+    P3 = 1,
+    Bt2020 = 2,
+}
+
+/// The display which techniques render for.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct OutputTarget {
+    pub encoding: OutputEncoding,
+    pub primaries: DisplayPrimaries,
+}
+
+// Nominal luminance of SDR white.
+const SDR_WHITE_NITS: f32 = 100.0;
+const DEFAULT_HDR_PEAK_NITS: f32 = 1000.0;
+
+impl OutputEncoding {
+    pub fn peak_nits(self) -> f32 {
+        match self {
+            Self::Srgb => SDR_WHITE_NITS,
+            Self::Pq { peak_nits } | Self::Hlg { peak_nits } | Self::ScRgb { peak_nits } => {
+                peak_nits
+            }
+        }
+    }
+
+    /// The primaries used unless others are requested: BT.2020 for BT.2100, and BT.709 otherwise.
+    pub fn default_primaries(self) -> DisplayPrimaries {
+        match self {
+            Self::Pq { .. } | Self::Hlg { .. } => DisplayPrimaries::Bt2020,
+            Self::Srgb | Self::ScRgb { .. } => DisplayPrimaries::Bt709,
+        }
+    }
+}
+
+impl DisplayPrimaries {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Bt709 => Self::P3,
+            Self::P3 => Self::Bt2020,
+            Self::Bt2020 => Self::Bt709,
+        }
+    }
+
+    /// CIE xy of red, green, blue, and white.
+    pub fn chromaticities(self) -> [[f32; 2]; 4] {
+        const D65: [f32; 2] = [0.3127, 0.3290];
+        match self {
+            Self::Bt709 => [[0.640, 0.330], [0.300, 0.600], [0.150, 0.060], D65],
+            Self::P3 => [[0.680, 0.320], [0.265, 0.690], [0.150, 0.060], D65],
+            Self::Bt2020 => [[0.708, 0.292], [0.170, 0.797], [0.131, 0.046], D65],
+        }
+    }
+
+    // ITU-T H.273 `ColourPrimaries`
+    fn cicp(self) -> u8 {
+        match self {
+            Self::Bt709 => 1,
+            Self::P3 => 12,
+            Self::Bt2020 => 9,
+        }
+    }
+}
+
+impl OutputTarget {
+    pub const SRGB: Self = Self {
+        encoding: OutputEncoding::Srgb,
+        primaries: DisplayPrimaries::Bt709,
+    };
+
+    /// `primaries` default to those of the encoding.
+    pub fn new(
+        encoding: OutputEncoding,
+        primaries: Option<DisplayPrimaries>,
+    ) -> anyhow::Result<Self> {
+        let primaries = primaries.unwrap_or_else(|| encoding.default_primaries());
+
+        anyhow::ensure!(
+            !matches!(encoding, OutputEncoding::ScRgb { .. })
+                || primaries == DisplayPrimaries::Bt709,
+            "scRGB always has BT.709 primaries"
+        );
+
+        Ok(Self {
+            encoding,
+            primaries,
+        })
+    }
+
+    /// Whether the framebuffer holds a non-linear signal, ready to be quantized.
+    pub fn is_encoded(self) -> bool {
+        matches!(
+            self.encoding,
+            OutputEncoding::Pq { .. } | OutputEncoding::Hlg { .. }
+        )
+    }
+
+    /// Whether the output is encoded for an HDR display, rather than as SDR sRGB.
+    pub fn is_hdr(self) -> bool {
+        !matches!(self.encoding, OutputEncoding::Srgb)
+    }
+
+    /// Coding-independent code points (ITU-T H.273) for the PNG `cICP` chunk:
+    /// primaries, transfer function, matrix, and full range.
+    pub fn cicp(self) -> Option<[u8; 4]> {
+        let transfer = match self.encoding {
+            OutputEncoding::Srgb => 13,
+            OutputEncoding::Pq { .. } => 16,
+            OutputEncoding::Hlg { .. } => 18,
+            OutputEncoding::ScRgb { .. } => return None,
+        };
+        Some([self.primaries.cicp(), transfer, 0, 1])
+    }
+
+    /// Sets the `output_encoding`, `output_peak_nits`, and `display_primaries` uniforms
+    /// of a technique.
+    pub fn bind(self, gl: &gl::Gl, shader_lib: &ShaderLib, shader_program: u32) {
+        // Must match the `OUTPUT_ENCODING_*` defines in `shaders/inc/output_target.glsl`
+        let encoding = match self.encoding {
+            OutputEncoding::Srgb => 0,
+            OutputEncoding::Pq { .. } => 1,
+            OutputEncoding::Hlg { .. } => 2,
+            OutputEncoding::ScRgb { .. } => 3,
+        };
+
+        unsafe {
+            let loc = shader_lib.uniform_location(gl, shader_program, "output_encoding");
+            if loc != -1 {
+                gl.Uniform1i(loc, encoding);
+            }
+
+            let loc = shader_lib.uniform_location(gl, shader_program, "output_peak_nits");
+            if loc != -1 {
+                gl.Uniform1f(loc, self.encoding.peak_nits());
+            }
+
+            let loc = shader_lib.uniform_location(gl, shader_program, "display_primaries");
+            if loc != -1 {
+                gl.Uniform1i(loc, self.primaries as i32);
+            }
+        }
+    }
+}
+
+impl fmt::Display for OutputEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Srgb => write!(f, "srgb"),
+            Self::Pq { peak_nits } => write!(f, "pq:{}", peak_nits),
+            Self::Hlg { peak_nits } => write!(f, "hlg:{}", peak_nits),
+            Self::ScRgb { peak_nits } => write!(f, "scrgb:{}", peak_nits),
+        }
+    }
+}
+
+impl fmt::Display for DisplayPrimaries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bt709 => write!(f, "bt709"),
+            Self::P3 => write!(f, "p3"),
+            Self::Bt2020 => write!(f, "bt2020"),
+        }
+    }
+}
+
+impl fmt::Display for OutputTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.encoding, self.primaries)
+    }
+}
+
+/// Parses `srgb`, `pq`, `hlg`, or `scrgb`, the latter three with an optional `:<peak nits>`.
+impl FromStr for OutputEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (name, peak_nits) = match s.split_once(':') {
+            Some((name, peak_nits)) => {
+                let peak_nits: f32 = peak_nits
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid peak luminance {:?}", peak_nits))?;
+                anyhow::ensure!(peak_nits > 0.0, "The peak luminance must be positive");
+                (name, Some(peak_nits))
+            }
+            None => (s, None),
+        };
+
+        if name == "srgb" {
+            anyhow::ensure!(
+                peak_nits.is_none(),
+                "sRGB output has no peak luminance; it's relative to the display"
+            );
+            return Ok(Self::Srgb);
+        }
+
+        let peak_nits = peak_nits.unwrap_or(DEFAULT_HDR_PEAK_NITS);
+        match name {
+            "pq" => Ok(Self::Pq { peak_nits }),
+            "hlg" => Ok(Self::Hlg { peak_nits }),
+            "scrgb" => Ok(Self::ScRgb { peak_nits }),
+            _ => Err(anyhow::anyhow!(
+                "Unknown output target {:?}; expected srgb, pq[:nits], hlg[:nits], or scrgb[:nits]",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for DisplayPrimaries {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "bt709" | "srgb" => Ok(Self::Bt709),
+            "p3" => Ok(Self::P3),
+            "bt2020" => Ok(Self::Bt2020),
+            _ => Err(anyhow::anyhow!(
+                "Unknown display primaries {:?}; expected bt709, p3, or bt2020",
+                s
+            )),
+        }
+    }
+}
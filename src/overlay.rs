@@ -0,0 +1,44 @@
+use crate::{vectorscope::VectorscopeMode, waveform::WaveformMode};
+
+/// A region of the window, in physical pixels, with the origin at the bottom-left corner.
+#[derive(Clone, Copy)]
+pub struct OverlayRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+pub struct OverlaySettings {
+    pub histogram: bool,
+    pub waveform: Option<WaveformMode>,
+    pub vectorscope: Option<VectorscopeMode>,
+    pub chromaticity: bool,
+    pub tone_curve: bool,
+    /// Index of the LUT to plot.
+    pub lut: Option<usize>,
+}
+
+/// Draws a full-screen-triangle pixel shader into `rect`, alpha-blended over the window contents.
+///
+/// `bind_inputs` is called with the program bound, so that it can set up any uniforms and buffers.
+pub fn draw_overlay(
+    gl: &gl::Gl,
+    shader_program: u32,
+    rect: OverlayRect,
+    bind_inputs: impl FnOnce(u32),
+) {
+    unsafe {
+        gl.Viewport(rect.x, rect.y, rect.width, rect.height);
+        gl.Enable(gl::BLEND);
+        gl.BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+
+        gl.UseProgram(shader_program);
+        bind_inputs(shader_program);
+        gl.DrawArrays(gl::TRIANGLES, 0, 3);
+        gl.UseProgram(0);
+
+        gl.Disable(gl::BLEND);
+    }
+}
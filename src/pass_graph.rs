@@ -0,0 +1,359 @@
+use anyhow::Context;
+use gl::types::GLenum;
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    fbo::Fbo, frame_constants::FrameConstants, lut_lib::LutLib, shader::ShaderKey,
+    shader_lib::ShaderLib, texture::Texture,
+};
+
+/// Comment which declares an intermediate pass of a technique, e.g.
+/// `// pass: shader=passes/blur_h.glsl scale=0.25 format=r16f`
+///
+/// Passes run in the order they're declared, before the technique itself, each into its own
+/// float render target. Later passes and the technique can sample it through a sampler
+/// uniform of its name, with the same UVs as `input_texture`.
+///
+/// * `shader`: path of the pass shader, relative to the technique; see `inc/pass_prelude.glsl`
+/// * `name`: name of the sampler uniform; defaults to the file stem of the shader
+/// * `scale`: size relative to the input image; 1 by default
+/// * `format`: `r16f`, `rg16f`, `rgba16f` (default), `r32f`, `rg32f`, or `rgba32f`
+/// * `mips`: `true` to generate a full mip chain after the pass, for `textureLod`
+const PASS_DECLARATION_PREFIX: &str = "// pass:";
+
+// The viewer draws a technique at a few sizes every frame: the image, the tone curve ramp,
+// and LUT plots.
+const MAX_CACHED_TARGET_SETS: usize = 4;
+
+#[derive(PartialEq)]
+pub struct PassDesc {
+    pub name: String,
+    pub shader_path: PathBuf,
+    pub scale: f32,
+    pub internal_format: GLenum,
+    pub mips: bool,
+}
+
+struct Pass {
+    desc: PassDesc,
+    shader: ShaderKey,
+}
+
+/// Render targets of every pass, for an input size.
+struct PassTargets {
+    input_size: [usize; 2],
+    outputs: Vec<Fbo>,
+}
+
+/// Passes of a technique; empty for most of them.
+#[derive(Default)]
+pub struct PassGraph {
+    passes: Vec<Pass>,
+    /// Set while the declarations fail to parse; the technique isn't drawn until they do.
+    invalid: bool,
+    /// Kept between draws, and reallocated only for new input sizes; most recently used first.
+    targets: RefCell<Vec<PassTargets>>,
+}
+
+impl PassDesc {
+    fn parse(declaration: &str, technique_dir: &Path) -> anyhow::Result<Self> {
+        let mut shader_path = None;
+        let mut name = None;
+        let mut scale = 1.0;
+        let mut internal_format = gl::RGBA16F;
+        let mut mips = false;
+
+        for entry in declaration.split_whitespace() {
+            let (key, value) = entry
+                .split_once('=')
+                .with_context(|| format!("Expected key=value, got {:?}", entry))?;
+
+            match key {
+                "shader" => shader_path = Some(technique_dir.join(value)),
+                "name" => name = Some(value.to_owned()),
+                "scale" => {
+                    scale = value
+                        .parse()
+                        .with_context(|| format!("Invalid pass scale {:?}", value))?;
+                    anyhow::ensure!(scale > 0.0, "The pass scale must be positive");
+                }
+                "format" => {
+                    internal_format = match value {
+                        "r16f" => gl::R16F,
+                        "rg16f" => gl::RG16F,
+                        "rgba16f" => gl::RGBA16F,
+                        "r32f" => gl::R32F,
+                        "rg32f" => gl::RG32F,
+                        "rgba32f" => gl::RGBA32F,
+                        _ => anyhow::bail!("Unsupported pass format {:?}", value),
+                    }
+                }
+                "mips" => mips = value.parse().context("Expected true or false")?,
+                _ => anyhow::bail!("Unknown pass declaration key {:?}", key),
+            }
+        }
+
+        let shader_path: PathBuf =
+            shader_path.context("The pass declaration must specify a shader")?;
+        anyhow::ensure!(shader_path.is_file(), "{:?} doesn't exist", shader_path);
+
+        Ok(Self {
+            name: name.unwrap_or_else(|| {
+                shader_path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            }),
+            shader_path,
+            scale,
+            internal_format,
+            mips,
+        })
+    }
+
+    fn size(&self, input_size: [usize; 2]) -> [usize; 2] {
+        input_size.map(|dim| ((dim as f32 * self.scale).round() as usize).max(1))
+    }
+
+    fn create_target(&self, gl: &gl::Gl, input_size: [usize; 2]) -> Fbo {
+        let size = self.size(input_size);
+        let mip_levels = if self.mips {
+            Texture::mip_level_count(size)
+        } else {
+            1
+        };
+        Fbo::with_mip_levels(gl, size, self.internal_format, mip_levels)
+    }
+}
+
+impl PassGraph {
+    /// Parses the pass declarations of the technique at `technique_path`, and adds their shaders
+    /// to `shader_lib`. See `PASS_DECLARATION_PREFIX` for the declaration syntax.
+    ///
+    /// Called again when the technique reloads; the passes and their render targets are only
+    /// replaced if the declarations changed.
+    pub fn load(
+        &mut self,
+        gl: &gl::Gl,
+        technique_path: &Path,
+        shader_lib: &mut ShaderLib,
+    ) -> anyhow::Result<()> {
+        let descs = match Self::parse_declarations(technique_path) {
+            Ok(descs) => descs,
+            Err(err) => {
+                self.invalid = true;
+                return Err(err);
+            }
+        };
+        self.invalid = false;
+
+        if self.passes.iter().map(|pass| &pass.desc).eq(&descs) {
+            return Ok(());
+        }
+
+        self.destroy_targets(gl);
+        self.passes = descs
+            .into_iter()
+            .map(|desc| Pass {
+                shader: shader_lib.add_pass_shader(&desc.shader_path),
+                desc,
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    fn parse_declarations(technique_path: &Path) -> anyhow::Result<Vec<PassDesc>> {
+        let source = std::fs::read_to_string(technique_path)
+            .with_context(|| format!("Reading {:?}", technique_path))?;
+        let technique_dir = technique_path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut descs: Vec<PassDesc> = Vec::new();
+        for (line_index, line) in source.lines().enumerate() {
+            let declaration = match line.trim().strip_prefix(PASS_DECLARATION_PREFIX) {
+                Some(declaration) => declaration,
+                None => continue,
+            };
+
+            let desc = PassDesc::parse(declaration, technique_dir)
+                .with_context(|| format!("Line {}", line_index + 1))?;
+            anyhow::ensure!(
+                descs.iter().all(|other| other.name != desc.name),
+                "Line {}: pass {:?} is declared twice",
+                line_index + 1,
+                desc.name
+            );
+
+            descs.push(desc);
+        }
+
+        Ok(descs)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// Whether any pass reads the uniform block `name`.
+    pub fn uses_uniform_block(&self, gl: &gl::Gl, shader_lib: &ShaderLib, name: &str) -> bool {
+        self.passes
+            .iter()
+            .filter_map(|pass| shader_lib.get_shader_gl_handle(&pass.shader))
+            .any(|program| shader_lib.uses_uniform_block(gl, program, name))
+    }
+
+    pub fn is_compiled(&self, shader_lib: &ShaderLib) -> bool {
+        !self.invalid
+            && self
+                .passes
+                .iter()
+                .all(|pass| shader_lib.get_shader_gl_handle(&pass.shader).is_some())
+    }
+
+    /// Renders every pass from `input`, which is bound to them as `input_texture`, and
+    /// `previous_pass` of the first one. Leaves the pass framebuffers bound; the outputs
+    /// are then bound with `bind_outputs`.
+    pub fn run(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        lut_lib: &LutLib,
+        input: &Texture,
+        frame: FrameConstants,
+    ) {
+        if self.passes.is_empty() {
+            return;
+        }
+
+        let mut targets = self.targets.borrow_mut();
+        let current = match targets
+            .iter()
+            .position(|targets| targets.input_size == input.size)
+        {
+            Some(index) => targets.remove(index),
+            None => PassTargets {
+                input_size: input.size,
+                outputs: self
+                    .passes
+                    .iter()
+                    .map(|pass| pass.desc.create_target(gl, input.size))
+                    .collect(),
+            },
+        };
+
+        for (pass_index, pass) in self.passes.iter().enumerate() {
+            let shader_program = match shader_lib.get_shader_gl_handle(&pass.shader) {
+                Some(shader_program) => shader_program,
+                None => break,
+            };
+
+            let (outputs, rest) = current.outputs.split_at(pass_index);
+            let fbo = &rest[0];
+            let size = fbo.texture.size;
+            fbo.bind(gl);
+
+            shader_lib.bind_frame_constants(
+                gl,
+                &FrameConstants {
+                    resolution: [size[0] as f32, size[1] as f32],
+                    ..frame
+                },
+            );
+
+            unsafe {
+                gl.Viewport(0, 0, size[0] as _, size[1] as _);
+                gl.UseProgram(shader_program);
+
+                let mut tex_unit = 0;
+                shader_lib.bind_sampler(gl, shader_program, "input_texture", input, &mut tex_unit);
+                shader_lib.bind_sampler(
+                    gl,
+                    shader_program,
+                    "previous_pass",
+                    outputs.last().map_or(input, |previous| &previous.texture),
+                    &mut tex_unit,
+                );
+                self.bind_samplers(gl, shader_lib, shader_program, outputs, &mut tex_unit);
+
+                for (lut_desc, lut_texture) in lut_lib.iter() {
+                    shader_lib.bind_sampler(
+                        gl,
+                        shader_program,
+                        &lut_desc.name,
+                        lut_texture,
+                        &mut tex_unit,
+                    );
+                }
+
+                let loc = shader_lib.uniform_location(gl, shader_program, "input_ev");
+                if loc != -1 {
+                    gl.Uniform1f(loc, frame.ev);
+                }
+
+                gl.DrawArrays(gl::TRIANGLES, 0, 3);
+                gl.UseProgram(0);
+            }
+
+            if pass.desc.mips {
+                fbo.texture.generate_mipmaps(gl);
+            }
+        }
+
+        targets.insert(0, current);
+        if targets.len() > MAX_CACHED_TARGET_SETS {
+            for evicted in targets.drain(MAX_CACHED_TARGET_SETS..) {
+                evicted.destroy(gl);
+            }
+        }
+    }
+
+    /// Binds the outputs of the last `run` to the samplers named after their passes.
+    pub fn bind_outputs(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        shader_program: u32,
+        tex_unit: &mut i32,
+    ) {
+        if let Some(current) = self.targets.borrow().first() {
+            self.bind_samplers(gl, shader_lib, shader_program, &current.outputs, tex_unit);
+        }
+    }
+
+    /// Frees the render targets; they're allocated again by the next `run`.
+    pub fn destroy_targets(&self, gl: &gl::Gl) {
+        for targets in self.targets.borrow_mut().drain(..) {
+            targets.destroy(gl);
+        }
+    }
+
+    fn bind_samplers(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        shader_program: u32,
+        outputs: &[Fbo],
+        tex_unit: &mut i32,
+    ) {
+        for (pass, output) in self.passes.iter().zip(outputs) {
+            shader_lib.bind_sampler(
+                gl,
+                shader_program,
+                &pass.desc.name,
+                &output.texture,
+                tex_unit,
+            );
+        }
+    }
+}
+
+impl PassTargets {
+    fn destroy(self, gl: &gl::Gl) {
+        for output in self.outputs {
+            output.destroy(gl);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    adaptation::AdaptationRates, debug_view::DebugView, frame_constants::TECHNIQUE_PARAM_COUNT,
+    output_target::OutputTarget,
+};
+
+/// How a captured image was produced; embedded in it, so that it can be traced back
+/// and reproduced.
+pub struct Provenance {
+    pub technique: String,
+    /// The `.glsl` or `.cube` file of the technique.
+    pub technique_file: PathBuf,
+    pub source_image: PathBuf,
+    pub ev: f64,
+    pub debug_view: DebugView,
+    pub output_target: OutputTarget,
+    /// `--params`
+    pub params: [f32; TECHNIQUE_PARAM_COUNT],
+    /// Of a batch sequence simulating eye adaptation; `None` for a single frame.
+    pub adaptation_frame_rate: Option<f64>,
+    pub adaptation_rates: AdaptationRates,
+    pub shader_revision: Option<ShaderRevision>,
+}
+
+/// Where the shaders came from, according to git.
+#[derive(Clone)]
+pub struct ShaderRevision {
+    /// Short hash of `HEAD`.
+    pub commit: String,
+    /// Whether anything in `shaders` differs from `HEAD`.
+    pub dirty: bool,
+    /// Blob hash of the technique file as it was rendered, even if not committed.
+    pub technique_blob: String,
+}
+
+impl ShaderRevision {
+    /// `None` if git isn't available, or the app doesn't run from a checkout.
+    pub fn of(technique_file: &Path) -> Option<Self> {
+        let git = |args: &[&str]| -> Option<String> {
+            let output = Command::new("git").args(args).output().ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+        };
+
+        let technique_file = technique_file.to_str()?;
+        Some(Self {
+            commit: git(&["rev-parse", "--short", "HEAD"])?,
+            dirty: !git(&["status", "--porcelain", "--", "shaders", technique_file])?.is_empty(),
+            technique_blob: git(&["hash-object", "--", technique_file])?,
+        })
+    }
+}
+
+impl Provenance {
+    /// Name and value pairs, in the order they're embedded.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            (
+                "Software",
+                format!("notorious6 {}", env!("CARGO_PKG_VERSION")),
+            ),
+            ("Technique", self.technique.clone()),
+            (
+                "TechniqueFile",
+                self.technique_file.to_string_lossy().into_owned(),
+            ),
+            (
+                "SourceImage",
+                self.source_image.to_string_lossy().into_owned(),
+            ),
+            ("EV", self.ev.to_string()),
+            (
+                "DebugView",
+                self.debug_view.name().unwrap_or("none").to_owned(),
+            ),
+            ("OutputTarget", self.output_target.to_string()),
+            (
+                "Params",
+                self.params
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+        ];
+
+        if let Some(frame_rate) = self.adaptation_frame_rate {
+            fields.push(("AdaptationFrameRate", frame_rate.to_string()));
+            fields.push(("AdaptToLight", self.adaptation_rates.to_light.to_string()));
+            fields.push(("AdaptToDark", self.adaptation_rates.to_dark.to_string()));
+        }
+
+        if let Some(revision) = &self.shader_revision {
+            let dirty = if revision.dirty { "-dirty" } else { "" };
+            fields.push(("ShaderCommit", format!("{}{}", revision.commit, dirty)));
+            fields.push(("TechniqueBlob", revision.technique_blob.clone()));
+        }
+
+        fields
+    }
+
+    /// An XMP packet with the fields as properties of the `n6` namespace.
+    pub fn to_xmp(&self) -> String {
+        let mut xmp = String::new();
+        xmp.push_str("<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n");
+        xmp.push_str("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n");
+        xmp.push_str(" <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n");
+        xmp.push_str("  <rdf:Description rdf:about=\"\"\n");
+        xmp.push_str("    xmlns:n6=\"https://github.com/h3r2tic/notorious6/ns/1.0/\"");
+        for (name, value) in self.fields() {
+            let _ = write!(xmp, "\n    n6:{}=\"{}\"", name, xml_escape(&value));
+        }
+        xmp.push_str("/>\n");
+        xmp.push_str(" </rdf:RDF>\n");
+        xmp.push_str("</x:xmpmeta>\n");
+        xmp.push_str("<?xpacket end=\"r\"?>");
+        xmp
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,108 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const DEFAULT_SCREENSHOT_DIR: &str = "screenshots";
+pub const DEFAULT_SCREENSHOT_NAME: &str = "{image}_{shader}_EV{ev}_{timestamp}";
+
+const PLACEHOLDERS: [&str; 4] = ["image", "shader", "ev", "timestamp"];
+
+/// Where screenshots go, and what they're called.
+pub struct ScreenshotNaming {
+    dir: PathBuf,
+    // File name without the extension, with `{image}`, `{shader}`, `{ev}`, and `{timestamp}`
+    // replaced by the image file stem, technique name, signed EV, and UTC time.
+    template: String,
+}
+
+impl Default for ScreenshotNaming {
+    fn default() -> Self {
+        Self {
+            dir: DEFAULT_SCREENSHOT_DIR.into(),
+            template: DEFAULT_SCREENSHOT_NAME.to_owned(),
+        }
+    }
+}
+
+impl ScreenshotNaming {
+    pub fn new(dir: PathBuf, template: String) -> anyhow::Result<Self> {
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("Unclosed `{{` in {:?}", template))?;
+            let placeholder = &rest[start + 1..start + end];
+            anyhow::ensure!(
+                PLACEHOLDERS.contains(&placeholder),
+                "Unknown placeholder {{{}}} in {:?}; expected {{{}}}",
+                placeholder,
+                template,
+                PLACEHOLDERS.join("}, {")
+            );
+            rest = &rest[start + end..];
+        }
+
+        Ok(Self { dir, template })
+    }
+
+    /// A path which doesn't exist yet; a counter is appended if the name is taken.
+    pub fn next_path(&self, image: &str, shader: &str, ev: f64, extension: &str) -> PathBuf {
+        let name = self
+            .template
+            .replace("{image}", image)
+            .replace("{shader}", shader)
+            .replace("{ev}", &format!("{:+.2}", ev))
+            .replace("{timestamp}", &utc_timestamp());
+
+        // Technique and image names could contain path separators.
+        let name: String = name
+            .chars()
+            .map(|c| {
+                if matches!(c, '/' | '\\' | ':') {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+
+        let mut file_path = self.dir.join(format!("{}.{}", name, extension));
+        let mut counter = 2;
+        while file_path.exists() {
+            file_path = self.dir.join(format!("{}_{}.{}", name, counter, extension));
+            counter += 1;
+        }
+
+        file_path
+    }
+}
+
+/// `YYYYMMDD-hhmmss`
+fn utc_timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+
+    // Howard Hinnant's `civil_from_days`.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
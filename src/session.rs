@@ -0,0 +1,445 @@
+use anyhow::Context;
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    debug_view::DebugView, image_diff::DiffMetric, output_target::DisplayPrimaries,
+    overlay::OverlaySettings, vectorscope::VectorscopeMode, waveform::WaveformMode,
+};
+
+pub const DEFAULT_SESSION_FILE: &str = "notorious6.session";
+
+const DEBUG_VIEW_NAMES: [(DebugView, &str); 4] = [
+    (DebugView::None, "none"),
+    (DebugView::FalseColor, "false_color"),
+    (DebugView::ClippedOutput, "clipped_output"),
+    (DebugView::OutOfGamutInput, "out_of_gamut_input"),
+];
+const WAVEFORM_MODE_NAMES: [(WaveformMode, &str); 2] = [
+    (WaveformMode::Luma, "luma"),
+    (WaveformMode::RgbParade, "rgb_parade"),
+];
+const VECTORSCOPE_MODE_NAMES: [(VectorscopeMode, &str); 2] = [
+    (VectorscopeMode::YCbCr, "ycbcr"),
+    (VectorscopeMode::Oklab, "oklab"),
+];
+
+/// An image, shown with a technique at an EV.
+#[derive(Clone, Default, PartialEq)]
+pub struct View {
+    pub image: PathBuf,
+    pub technique: String,
+    pub ev: f64,
+    pub debug_view: DebugView,
+}
+
+/// A `View` recalled with a number key.
+#[derive(Clone, PartialEq)]
+pub struct Bookmark {
+    /// 1 to 9.
+    pub key: u8,
+    pub name: String,
+    pub view: View,
+}
+
+/// Viewer state restored on start. Images are identified by path, and techniques by name,
+/// so that it survives files being added or removed.
+#[derive(Default, PartialEq)]
+pub struct Session {
+    pub view: Option<View>,
+    // EV last used with each image.
+    pub image_ev: Vec<(PathBuf, f64)>,
+    pub auto_exposure: bool,
+    pub adaptation: Option<bool>,
+    pub display_primaries: Option<DisplayPrimaries>,
+    pub overlays: OverlaySettings,
+    pub diff_baseline: Option<String>,
+    pub diff_view: Option<DiffMetric>,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmark {
+    /// File name stem for captures of the bookmark.
+    pub fn file_stem(&self) -> String {
+        let name: String = self
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || "+-._ ".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{} - {}", self.key, name)
+    }
+}
+
+impl Session {
+    /// A missing file is an empty session.
+    pub fn load(file_path: &Path) -> anyhow::Result<Self> {
+        if !file_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(file_path)
+            .with_context(|| format!("Reading {:?}", file_path))?;
+        Self::parse(&contents).with_context(|| format!("Parsing {:?}", file_path))
+    }
+
+    pub fn save(&self, file_path: &Path) -> anyhow::Result<()> {
+        std::fs::write(file_path, self.to_string())
+            .with_context(|| format!("Writing {:?}", file_path))
+    }
+
+    /// `key = value` lines, with `[image_ev]` and `[bookmark <key>]` sections.
+    fn parse(contents: &str) -> anyhow::Result<Self> {
+        enum Section {
+            Main,
+            ImageEv,
+            Bookmark(Bookmark),
+        }
+
+        let mut session = Self::default();
+        let mut view = ViewBuilder::default();
+        let mut section = Section::Main;
+
+        // Finishes the current section when a new one starts, and at the end.
+        let finish_section =
+            |section: Section, session: &mut Self, view: &mut ViewBuilder| -> anyhow::Result<()> {
+                let view = std::mem::take(view);
+                match section {
+                    Section::Main => session.view = view.build(),
+                    Section::ImageEv => {}
+                    Section::Bookmark(mut bookmark) => {
+                        bookmark.view = view.build().with_context(|| {
+                            format!("Bookmark {} needs an image and a technique", bookmark.key)
+                        })?;
+                        session.bookmarks.retain(|b| b.key != bookmark.key);
+                        session.bookmarks.push(bookmark);
+                    }
+                }
+                Ok(())
+            };
+
+        for (line_index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let result = (|| -> anyhow::Result<()> {
+                if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                    let new_section = match header.split_once(' ') {
+                        None if header == "image_ev" => Section::ImageEv,
+                        Some(("bookmark", key)) => {
+                            let key: u8 = key.parse().context("Invalid bookmark key")?;
+                            anyhow::ensure!((1..=9).contains(&key), "Bookmark keys are 1 to 9");
+                            Section::Bookmark(Bookmark {
+                                key,
+                                name: format!("Bookmark {}", key),
+                                view: View::default(),
+                            })
+                        }
+                        _ => anyhow::bail!("Unknown section {:?}", header),
+                    };
+                    finish_section(
+                        std::mem::replace(&mut section, new_section),
+                        &mut session,
+                        &mut view,
+                    )?;
+                    return Ok(());
+                }
+
+                if let Section::ImageEv = section {
+                    let (ev, image) = line
+                        .split_once(' ')
+                        .context("Expected `<ev> <image path>`")?;
+                    session
+                        .image_ev
+                        .push((image.trim().into(), ev.parse().context("Invalid EV")?));
+                    return Ok(());
+                }
+
+                let (key, value) = line.split_once('=').context("Expected `key = value`")?;
+                let (key, value) = (key.trim(), value.trim());
+
+                if view.set(key, value)? {
+                    return Ok(());
+                }
+
+                match (&mut section, key) {
+                    (Section::Bookmark(bookmark), "name") => bookmark.name = value.to_owned(),
+                    (Section::Main, "auto_exposure") => {
+                        session.auto_exposure = value.parse().context("Expected true or false")?
+                    }
+                    (Section::Main, "adaptation") => {
+                        session.adaptation = Some(value.parse().context("Expected true or false")?)
+                    }
+                    (Section::Main, "display_primaries") => {
+                        session.display_primaries = Some(value.parse()?)
+                    }
+                    (Section::Main, "histogram") => {
+                        session.overlays.histogram =
+                            value.parse().context("Expected true or false")?
+                    }
+                    (Section::Main, "waveform") => {
+                        session.overlays.waveform = parse_optional(value, &WAVEFORM_MODE_NAMES)?
+                    }
+                    (Section::Main, "vectorscope") => {
+                        session.overlays.vectorscope =
+                            parse_optional(value, &VECTORSCOPE_MODE_NAMES)?
+                    }
+                    (Section::Main, "chromaticity") => {
+                        session.overlays.chromaticity =
+                            value.parse().context("Expected true or false")?
+                    }
+                    (Section::Main, "tone_curve") => {
+                        session.overlays.tone_curve =
+                            value.parse().context("Expected true or false")?
+                    }
+                    (Section::Main, "diff_baseline") => {
+                        session.diff_baseline = (value != "none").then(|| value.to_owned())
+                    }
+                    (Section::Main, "diff_view") => {
+                        let metrics = DiffMetric::ALL.map(|metric| (metric, metric.short_name()));
+                        session.diff_view = parse_optional(value, &metrics)?
+                    }
+                    _ => anyhow::bail!("Unknown key {:?}", key),
+                }
+
+                Ok(())
+            })();
+
+            result.with_context(|| format!("Line {}", line_index + 1))?;
+        }
+
+        finish_section(section, &mut session, &mut view)?;
+        session.bookmarks.sort_by_key(|bookmark| bookmark.key);
+
+        Ok(session)
+    }
+}
+
+impl std::fmt::Display for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        let bool_str = |v: bool| if v { "true" } else { "false" };
+
+        writeln!(out, "# notorious6 session; saved on exit")?;
+        if let Some(view) = &self.view {
+            write_view(&mut out, view)?;
+        }
+        writeln!(out, "auto_exposure = {}", bool_str(self.auto_exposure))?;
+        if let Some(adaptation) = self.adaptation {
+            writeln!(out, "adaptation = {}", bool_str(adaptation))?;
+        }
+        if let Some(display_primaries) = self.display_primaries {
+            writeln!(out, "display_primaries = {}", display_primaries)?;
+        }
+        writeln!(out, "histogram = {}", bool_str(self.overlays.histogram))?;
+        writeln!(
+            out,
+            "waveform = {}",
+            optional_name(self.overlays.waveform, &WAVEFORM_MODE_NAMES)
+        )?;
+        writeln!(
+            out,
+            "vectorscope = {}",
+            optional_name(self.overlays.vectorscope, &VECTORSCOPE_MODE_NAMES)
+        )?;
+        writeln!(
+            out,
+            "chromaticity = {}",
+            bool_str(self.overlays.chromaticity)
+        )?;
+        writeln!(out, "tone_curve = {}", bool_str(self.overlays.tone_curve))?;
+        writeln!(
+            out,
+            "diff_baseline = {}",
+            self.diff_baseline.as_deref().unwrap_or("none")
+        )?;
+        writeln!(
+            out,
+            "diff_view = {}",
+            self.diff_view.map_or("none", DiffMetric::short_name)
+        )?;
+
+        if !self.image_ev.is_empty() {
+            writeln!(out, "\n[image_ev]")?;
+            for (image, ev) in &self.image_ev {
+                writeln!(out, "{} {}", ev, image.display())?;
+            }
+        }
+
+        for bookmark in &self.bookmarks {
+            writeln!(out, "\n[bookmark {}]", bookmark.key)?;
+            writeln!(out, "name = {}", bookmark.name)?;
+            write_view(&mut out, &bookmark.view)?;
+        }
+
+        f.write_str(&out)
+    }
+}
+
+#[derive(Default)]
+struct ViewBuilder {
+    image: Option<PathBuf>,
+    technique: Option<String>,
+    ev: f64,
+    debug_view: DebugView,
+}
+
+impl ViewBuilder {
+    /// Returns whether `key` is a field of `View`.
+    fn set(&mut self, key: &str, value: &str) -> anyhow::Result<bool> {
+        match key {
+            "image" => self.image = Some(value.into()),
+            "technique" => self.technique = Some(value.to_owned()),
+            "ev" => self.ev = value.parse().context("Invalid EV")?,
+            "debug_view" => self.debug_view = parse_name(value, &DEBUG_VIEW_NAMES)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn build(self) -> Option<View> {
+        Some(View {
+            image: self.image?,
+            technique: self.technique?,
+            ev: self.ev,
+            debug_view: self.debug_view,
+        })
+    }
+}
+
+fn write_view(out: &mut String, view: &View) -> std::fmt::Result {
+    writeln!(out, "image = {}", view.image.display())?;
+    writeln!(out, "technique = {}", view.technique)?;
+    writeln!(out, "ev = {}", view.ev)?;
+    writeln!(
+        out,
+        "debug_view = {}",
+        name_of(view.debug_view, &DEBUG_VIEW_NAMES)
+    )
+}
+
+fn name_of<T: PartialEq>(value: T, names: &[(T, &'static str)]) -> &'static str {
+    names
+        .iter()
+        .find(|(v, _)| *v == value)
+        .map_or("unknown", |(_, name)| name)
+}
+
+fn optional_name<T: PartialEq>(value: Option<T>, names: &[(T, &'static str)]) -> &'static str {
+    value.map_or("none", |value| name_of(value, names))
+}
+
+fn parse_name<T: Copy>(s: &str, names: &[(T, &str)]) -> anyhow::Result<T> {
+    names
+        .iter()
+        .find(|(_, name)| *name == s)
+        .map(|(v, _)| *v)
+        .ok_or_else(|| {
+            let expected: Vec<&str> = names.iter().map(|(_, name)| *name).collect();
+            anyhow::anyhow!("Unknown value {:?}; expected {}", s, expected.join(", "))
+        })
+}
+
+fn parse_optional<T: Copy>(s: &str, names: &[(T, &str)]) -> anyhow::Result<Option<T>> {
+    if s == "none" {
+        Ok(None)
+    } else {
+        parse_name(s, names).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(image: &str, technique: &str, ev: f64) -> View {
+        View {
+            image: image.into(),
+            technique: technique.to_owned(),
+            ev,
+            debug_view: DebugView::None,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let session = Session {
+            view: Some(View {
+                debug_view: DebugView::FalseColor,
+                ..view("img/sunset.exr", "tony-mc-mapface", 1.5)
+            }),
+            image_ev: vec![
+                ("img/with spaces/night sky.exr".into(), -2.25),
+                ("img/sunset.exr".into(), 1.5),
+            ],
+            auto_exposure: true,
+            adaptation: Some(false),
+            display_primaries: Some(DisplayPrimaries::P3),
+            overlays: OverlaySettings {
+                histogram: true,
+                waveform: Some(WaveformMode::RgbParade),
+                vectorscope: None,
+                chromaticity: false,
+                tone_curve: true,
+                ..Default::default()
+            },
+            diff_baseline: Some("linear".to_owned()),
+            diff_view: Some(DiffMetric::ALL[0]),
+            bookmarks: vec![
+                Bookmark {
+                    key: 1,
+                    name: "Blown-out sky".to_owned(),
+                    view: view("img/with spaces/night sky.exr", "linear", -2.25),
+                },
+                Bookmark {
+                    key: 9,
+                    name: "Bookmark 9".to_owned(),
+                    view: View {
+                        debug_view: DebugView::ClippedOutput,
+                        ..view("img/sunset.exr", "linear", 0.0)
+                    },
+                },
+            ],
+        };
+
+        let text = session.to_string();
+        let parsed = Session::parse(&text).unwrap();
+        assert!(parsed == session, "{}", text);
+        assert_eq!(parsed.to_string(), text);
+    }
+
+    #[test]
+    fn round_trips_empty() {
+        let session = Session::default();
+        assert!(Session::parse(&session.to_string()).unwrap() == session);
+    }
+
+    #[test]
+    fn rejects_bookmark_keys_outside_1_to_9() {
+        for key in [0, 10] {
+            let text = format!("[bookmark {}]\nimage = a.exr\ntechnique = linear\n", key);
+            assert!(Session::parse(&text).is_err(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_sections() {
+        assert!(Session::parse("[bookmarks 1]\n").is_err());
+        assert!(Session::parse("[view]\nev = 1\n").is_err());
+    }
+
+    #[test]
+    fn rejects_lines_without_equals() {
+        assert!(Session::parse("auto_exposure true\n").is_err());
+        assert!(Session::parse("[bookmark 2]\nimage a.exr\ntechnique = linear\n").is_err());
+    }
+}
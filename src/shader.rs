@@ -4,7 +4,12 @@ use gl::types::*;
 use relative_path::RelativePathBuf;
 use shader_prepper::gl_compiler::{compile_shader, ShaderCompilerOutput};
 use std::sync::Arc;
-use std::{ffi::CString, path::PathBuf};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::CString,
+    path::{Path, PathBuf},
+};
 use turbosloth::*;
 
 #[derive(Clone, Hash)]
@@ -174,6 +179,72 @@ pub(crate) fn make_program(gl: &gl::Gl, shaders: &[u32]) -> anyhow::Result<u32>
     }
 }
 
+pub(crate) fn dispatch_compute(gl: &gl::Gl, shader_program: u32, size: [u32; 3]) {
+    unsafe {
+        let mut work_group_size: [i32; 3] = [0, 0, 0];
+        gl.GetProgramiv(
+            shader_program,
+            gl::COMPUTE_WORK_GROUP_SIZE,
+            &mut work_group_size[0],
+        );
+
+        fn div_up(a: u32, b: u32) -> u32 {
+            (a + b - 1) / b
+        }
+
+        gl.DispatchCompute(
+            div_up(size[0], work_group_size[0] as u32),
+            div_up(size[1], work_group_size[1] as u32),
+            div_up(size[2], work_group_size[2] as u32),
+        );
+    }
+}
+
+/// `glGetUniformLocation` and `glGetUniformBlockIndex`, looked up once per program.
+#[derive(Default)]
+pub(crate) struct UniformCache {
+    // Per program, by name; -1 for uniforms the program doesn't have.
+    locations: RefCell<HashMap<u32, HashMap<String, i32>>>,
+    // Per program, by name; `INVALID_INDEX` for blocks the program doesn't have.
+    block_indices: RefCell<HashMap<u32, HashMap<String, u32>>>,
+}
+
+impl UniformCache {
+    pub fn location(&self, gl: &gl::Gl, shader_program: u32, name: &str) -> i32 {
+        let mut locations = self.locations.borrow_mut();
+        let locations = locations.entry(shader_program).or_default();
+
+        if let Some(&loc) = locations.get(name) {
+            return loc;
+        }
+
+        let c_name = CString::new(name).unwrap();
+        let loc = unsafe { gl.GetUniformLocation(shader_program, c_name.as_ptr()) };
+        locations.insert(name.to_owned(), loc);
+        loc
+    }
+
+    pub fn block_index(&self, gl: &gl::Gl, shader_program: u32, name: &str) -> u32 {
+        let mut block_indices = self.block_indices.borrow_mut();
+        let block_indices = block_indices.entry(shader_program).or_default();
+
+        if let Some(&index) = block_indices.get(name) {
+            return index;
+        }
+
+        let c_name = CString::new(name).unwrap();
+        let index = unsafe { gl.GetUniformBlockIndex(shader_program, c_name.as_ptr()) };
+        block_indices.insert(name.to_owned(), index);
+        index
+    }
+
+    /// Drops the lookups of a program which has been replaced.
+    pub fn forget(&mut self, shader_program: u32) {
+        self.locations.get_mut().remove(&shader_program);
+        self.block_indices.get_mut().remove(&shader_program);
+    }
+}
+
 #[derive(Hash, PartialEq, Eq, Clone, PartialOrd, Ord)]
 pub struct ShaderKey {
     path: PathBuf,
@@ -184,6 +255,10 @@ impl ShaderKey {
         Self { path: path.into() }
     }
 
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     pub fn name(&self) -> String {
         self.path
             .file_stem()
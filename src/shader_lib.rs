@@ -5,17 +5,35 @@ use std::path::Path;
 use std::sync::Arc;
 use turbosloth::*;
 
-use crate::shader::*;
+use crate::{
+    buffer::Buffer,
+    frame_constants::{FrameConstants, FRAME_CONSTANTS_BINDING},
+    shader::*,
+    texture::Texture,
+};
+
+#[derive(Clone, Copy)]
+enum ShaderKind {
+    // Full-screen pixel shader, compiled with the shared vertex shader and postamble.
+    Fragment,
+    // Like `Fragment`, but its output is the same way up as its inputs, so that it can be
+    // sampled with the same UVs by later passes.
+    Pass,
+    // Compute shader with its own `main`.
+    Compute,
+}
 
 struct CompiledShader {
-    preprocessed_ps: turbosloth::Lazy<PreprocessedShader>,
+    kind: ShaderKind,
+    preprocessed: turbosloth::Lazy<PreprocessedShader>,
     gl_handle: Option<u32>,
 }
 
 impl CompiledShader {
-    fn new(preprocessed_ps: turbosloth::Lazy<PreprocessedShader>) -> Self {
+    fn new(kind: ShaderKind, preprocessed: turbosloth::Lazy<PreprocessedShader>) -> Self {
         Self {
-            preprocessed_ps,
+            kind,
+            preprocessed,
             gl_handle: None,
         }
     }
@@ -24,7 +42,10 @@ impl CompiledShader {
 pub struct ShaderLib {
     shaders: HashMap<ShaderKey, CompiledShader>,
     vs_handle: u32,
+    pass_vs_handle: u32,
     lazy_cache: Arc<LazyCache>,
+    uniforms: UniformCache,
+    frame_constants_buffer: Buffer,
 }
 
 pub enum AnyShadersChanged {
@@ -50,20 +71,50 @@ impl ShaderLib {
         let vs = make_shader(gl, gl::VERTEX_SHADER, once(&source))
             .expect("Vertex shader failed to compile");
 
+        let pass_source = shader_prepper::SourceChunk::from_file_source(
+            "no_file",
+            r#"
+                #version 430
+                out vec2 input_uv;
+                void main()
+                {
+                    input_uv = vec2(gl_VertexID & 1, gl_VertexID >> 1) * 2.0;
+                    gl_Position = vec4(input_uv * 2.0 - 1.0, 0, 1);
+                }"#,
+        );
+
+        let pass_vs = make_shader(gl, gl::VERTEX_SHADER, once(&pass_source))
+            .expect("Vertex shader failed to compile");
+
         Self {
             shaders: Default::default(),
             vs_handle: vs,
+            pass_vs_handle: pass_vs,
             lazy_cache: lazy_cache.clone(),
+            uniforms: Default::default(),
+            frame_constants_buffer: Buffer::new(gl, std::mem::size_of::<FrameConstants>()),
         }
     }
 
     pub fn add_shader(&mut self, path: impl AsRef<Path>) -> ShaderKey {
+        self.add_shader_of_kind(ShaderKind::Fragment, path)
+    }
+
+    pub fn add_pass_shader(&mut self, path: impl AsRef<Path>) -> ShaderKey {
+        self.add_shader_of_kind(ShaderKind::Pass, path)
+    }
+
+    pub fn add_compute_shader(&mut self, path: impl AsRef<Path>) -> ShaderKey {
+        self.add_shader_of_kind(ShaderKind::Compute, path)
+    }
+
+    fn add_shader_of_kind(&mut self, kind: ShaderKind, path: impl AsRef<Path>) -> ShaderKey {
         let path = path.as_ref().to_owned();
         let key = ShaderKey::new(&path);
-        self.shaders.insert(
-            key.clone(),
-            CompiledShader::new(PreprocessShader { path }.into_lazy()),
-        );
+        // Shared by techniques, e.g. pass shaders; those already added keep their program.
+        self.shaders
+            .entry(key.clone())
+            .or_insert_with(|| CompiledShader::new(kind, PreprocessShader { path }.into_lazy()));
         key
     }
 
@@ -71,6 +122,45 @@ impl ShaderLib {
         self.shaders.get(shader).and_then(|shader| shader.gl_handle)
     }
 
+    /// `glGetUniformLocation`, looked up once per program.
+    pub fn uniform_location(&self, gl: &gl::Gl, shader_program: u32, name: &str) -> i32 {
+        self.uniforms.location(gl, shader_program, name)
+    }
+
+    /// Whether `shader_program` reads the uniform block `name`; unused ones are optimized out.
+    pub fn uses_uniform_block(&self, gl: &gl::Gl, shader_program: u32, name: &str) -> bool {
+        self.uniforms.block_index(gl, shader_program, name) != gl::INVALID_INDEX
+    }
+
+    /// Binds `texture` to the texture unit `tex_unit`, and advances it, if `shader_program`
+    /// has a sampler called `name`.
+    pub fn bind_sampler(
+        &self,
+        gl: &gl::Gl,
+        shader_program: u32,
+        name: &str,
+        texture: &Texture,
+        tex_unit: &mut i32,
+    ) {
+        let loc = self.uniform_location(gl, shader_program, name);
+        if loc != -1 {
+            unsafe {
+                gl.ActiveTexture(gl::TEXTURE0 + *tex_unit as gl::types::GLenum);
+                gl.BindTexture(texture.ty, texture.id);
+                gl.Uniform1i(loc, *tex_unit);
+            }
+            *tex_unit += 1;
+        }
+    }
+
+    /// Uploads `constants`, and binds them for the `FrameConstants` uniform block.
+    pub fn bind_frame_constants(&self, gl: &gl::Gl, constants: &FrameConstants) {
+        self.frame_constants_buffer
+            .write(gl, std::slice::from_ref(constants));
+        self.frame_constants_buffer
+            .bind_uniform(gl, FRAME_CONSTANTS_BINDING);
+    }
+
     pub fn compile_all(&mut self, gl: &gl::Gl) -> AnyShadersChanged {
         let mut any_shaders_changed = AnyShadersChanged::No;
 
@@ -84,22 +174,40 @@ impl ShaderLib {
         );
 
         for shader in self.shaders.values_mut() {
-            if !shader.preprocessed_ps.is_up_to_date() {
-                let handle: anyhow::Result<u32> =
-                    smol::block_on(shader.preprocessed_ps.eval(&self.lazy_cache))
-                        .context("Preprocessing")
-                        .and_then(|ps_src| {
-                            let sources = ps_src.source.iter().chain(once(&ps_postamble));
-
-                            make_shader(gl, gl::FRAGMENT_SHADER, sources)
+            if !shader.preprocessed.is_up_to_date() {
+                let preprocessed = smol::block_on(shader.preprocessed.eval(&self.lazy_cache))
+                    .context("Preprocessing");
+
+                let handle: anyhow::Result<u32> = match shader.kind {
+                    ShaderKind::Fragment | ShaderKind::Pass => {
+                        let vs_handle = match shader.kind {
+                            ShaderKind::Pass => self.pass_vs_handle,
+                            _ => self.vs_handle,
+                        };
+
+                        preprocessed
+                            .and_then(|ps_src| {
+                                let sources = ps_src.source.iter().chain(once(&ps_postamble));
+
+                                make_shader(gl, gl::FRAGMENT_SHADER, sources)
+                            })
+                            .context("Compiling the pixel shader")
+                            .and_then(|ps| make_program(gl, &[vs_handle, ps]))
+                    }
+                    ShaderKind::Compute => preprocessed
+                        .and_then(|cs_src| {
+                            make_shader(gl, gl::COMPUTE_SHADER, cs_src.source.iter())
                         })
-                        .context("Compiling the pixel shader")
-                        .and_then(|ps| make_program(gl, &[self.vs_handle, ps]));
+                        .context("Compiling the compute shader")
+                        .and_then(|cs| make_program(gl, &[cs])),
+                };
 
                 match handle {
                     Ok(handle) => {
                         log::info!("Shader compiled.");
-                        shader.gl_handle = Some(handle);
+                        if let Some(old_handle) = shader.gl_handle.replace(handle) {
+                            self.uniforms.forget(old_handle);
+                        }
                         any_shaders_changed = AnyShadersChanged::Yes;
                     }
                     Err(err) => log::error!("Shader failed to compile: {:?}", err),
@@ -1,4 +1,5 @@
 use gl::types::{GLenum, GLuint};
+use std::ffi::c_void;
 
 use crate::image_loading::ImageRgb32f;
 
@@ -92,26 +93,184 @@ impl Texture {
         res
     }
 
-    pub fn new_1d(gl: &gl::Gl, width: u32, internal_format: GLenum) -> Self {
+    pub fn new_2d_empty(gl: &gl::Gl, size: [usize; 2], internal_format: GLenum) -> Self {
+        Self::new_2d_empty_mipmapped(gl, size, internal_format, 1)
+    }
+
+    /// Trilinearly filtered if `mip_levels` is more than 1; see `generate_mipmaps`.
+    pub fn new_2d_empty_mipmapped(
+        gl: &gl::Gl,
+        size: [usize; 2],
+        internal_format: GLenum,
+        mip_levels: usize,
+    ) -> Self {
+        let ty = gl::TEXTURE_2D;
+        let min_filter = if mip_levels > 1 {
+            gl::LINEAR_MIPMAP_LINEAR
+        } else {
+            gl::LINEAR
+        };
+
+        unsafe {
+            let mut texture_id = 0;
+            gl.GenTextures(1, &mut texture_id);
+            gl.BindTexture(ty, texture_id);
+            gl.TexStorage2D(
+                ty,
+                mip_levels as _,
+                internal_format,
+                size[0] as _,
+                size[1] as _,
+            );
+            gl.TexParameteri(ty, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+            Texture {
+                ty,
+                id: texture_id,
+                size,
+                internal_format,
+            }
+        }
+    }
+
+    /// Empty storage for a 1D, 2D, or 3D texture, depending on which of `size` are larger than 1.
+    /// Only the width and height are recorded in `Texture::size`.
+    pub fn new_storage(
+        gl: &gl::Gl,
+        size: [u32; 3],
+        internal_format: GLenum,
+        filter: GLenum,
+        wrap: GLenum,
+    ) -> Self {
+        let [width, height, depth] = size;
+        let ty = if depth > 1 {
+            gl::TEXTURE_3D
+        } else if height > 1 {
+            gl::TEXTURE_2D
+        } else {
+            gl::TEXTURE_1D
+        };
+
+        unsafe {
+            let mut texture_id = 0;
+            gl.GenTextures(1, &mut texture_id);
+            gl.BindTexture(ty, texture_id);
+
+            match ty {
+                gl::TEXTURE_3D => {
+                    gl.TexStorage3D(ty, 1, internal_format, width as _, height as _, depth as _)
+                }
+                gl::TEXTURE_2D => gl.TexStorage2D(ty, 1, internal_format, width as _, height as _),
+                _ => gl.TexStorage1D(ty, 1, internal_format, width as _),
+            }
+
+            gl.TexParameteri(ty, gl::TEXTURE_MIN_FILTER, filter as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_MAG_FILTER, filter as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_WRAP_S, wrap as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_WRAP_T, wrap as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_WRAP_R, wrap as i32);
+
+            Texture {
+                ty,
+                id: texture_id,
+                size: [width as _, height as _],
+                internal_format,
+            }
+        }
+    }
+
+    /// A clamped RGB32F 1D texture filled with `data`.
+    pub fn new_1d_rgb32f(gl: &gl::Gl, data: &[[f32; 3]]) -> Self {
         let ty = gl::TEXTURE_1D;
+        let internal_format = gl::RGB32F;
 
         unsafe {
             let mut texture_id = 0;
             gl.GenTextures(1, &mut texture_id);
             gl.BindTexture(ty, texture_id);
-            gl.TexStorage1D(ty, 1, internal_format, width as _);
+            gl.TexStorage1D(ty, 1, internal_format, data.len() as _);
+            gl.TexSubImage1D(
+                ty,
+                0,
+                0,
+                data.len() as _,
+                gl::RGB,
+                gl::FLOAT,
+                data.as_ptr() as *const c_void,
+            );
             gl.TexParameteri(ty, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
             gl.TexParameteri(ty, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+
+            Texture {
+                ty,
+                id: texture_id,
+                size: [data.len(), 1],
+                internal_format,
+            }
+        }
+    }
+
+    /// A clamped RGB32F 3D texture `size` texels along each axis, filled with `data`
+    /// in which red varies fastest. Only the width and height are recorded in `Texture::size`.
+    pub fn new_3d_rgb32f(gl: &gl::Gl, size: usize, data: &[[f32; 3]]) -> Self {
+        assert_eq!(data.len(), size * size * size);
 
-            // TODO: make configurable
-            gl.TexParameteri(ty, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+        let ty = gl::TEXTURE_3D;
+        let internal_format = gl::RGB32F;
+
+        unsafe {
+            let mut texture_id = 0;
+            gl.GenTextures(1, &mut texture_id);
+            gl.BindTexture(ty, texture_id);
+            gl.TexStorage3D(ty, 1, internal_format, size as _, size as _, size as _);
+            gl.TexSubImage3D(
+                ty,
+                0,
+                0,
+                0,
+                0,
+                size as _,
+                size as _,
+                size as _,
+                gl::RGB,
+                gl::FLOAT,
+                data.as_ptr() as *const c_void,
+            );
+            gl.TexParameteri(ty, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl.TexParameteri(ty, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as i32);
 
             Texture {
                 ty,
                 id: texture_id,
-                size: [width as _, 1],
+                size: [size, size],
                 internal_format,
             }
         }
     }
+
+    /// Of a full mip chain, down to 1x1.
+    pub fn mip_level_count(size: [usize; 2]) -> usize {
+        (usize::BITS - size[0].max(size[1]).max(1).leading_zeros()) as usize
+    }
+
+    /// Downsamples the top level into the rest of the mip chain.
+    pub fn generate_mipmaps(&self, gl: &gl::Gl) {
+        unsafe {
+            gl.BindTexture(self.ty, self.id);
+            gl.GenerateMipmap(self.ty);
+        }
+    }
+
+    pub fn destroy(self, gl: &gl::Gl) {
+        unsafe {
+            gl.DeleteTextures(1, &self.id);
+        }
+    }
 }
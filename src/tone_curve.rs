@@ -0,0 +1,176 @@
+use std::ffi::c_void;
+
+use crate::{
+    buffer::Buffer,
+    fbo::Fbo,
+    image_loading::ImageRgb32f,
+    overlay::{draw_overlay, OverlayRect},
+    shader::ShaderKey,
+    shader_lib::ShaderLib,
+    texture::Texture,
+};
+
+// Must match the `TONE_CURVE_*` defines in `shaders/scope/tone_curve_draw.glsl`
+const TONE_CURVE_SAMPLE_COUNT: usize = 256;
+const TONE_CURVE_LOG2_MIN: f32 = -10.0;
+const TONE_CURVE_LOG2_MAX: f32 = 6.0;
+const TONE_CURVE_MAX_PINNED: usize = 4;
+
+// Grey, then saturated BT.709 red, green, and blue, each scaled to unit luminance.
+const TONE_CURVE_RAMP_COLORS: [[f32; 3]; 4] = [
+    [1.0, 1.0, 1.0],
+    [1.0 / 0.2126, 0.0, 0.0],
+    [0.0, 1.0 / 0.7152, 0.0],
+    [0.0, 0.0, 1.0 / 0.0722],
+];
+
+pub const TONE_CURVE_OVERLAY_SIZE: [i32; 2] = [512, 320];
+
+/// Output luminance of a technique for each of `TONE_CURVE_RAMP_COLORS`,
+/// at `TONE_CURVE_SAMPLE_COUNT` log2 stimulus values.
+#[derive(Clone)]
+pub struct ToneCurves {
+    pub technique_name: String,
+    luminance: Vec<f32>,
+}
+
+impl ToneCurves {
+    fn grey(&self) -> &[f32] {
+        &self.luminance[..TONE_CURVE_SAMPLE_COUNT]
+    }
+}
+
+/// Plots the response of techniques to exposure ramps: output luminance vs log2 input stimulus.
+/// Curves of other techniques can be pinned for comparison.
+pub struct ToneCurvePlot {
+    ramp: Texture,
+    draw_shader: ShaderKey,
+    curves_buffer: Buffer,
+    current: Option<ToneCurves>,
+    pinned: Vec<ToneCurves>,
+}
+
+impl ToneCurvePlot {
+    pub fn new(shader_lib: &mut ShaderLib, gl: &gl::Gl) -> Self {
+        let mut ramp = ImageRgb32f::new(TONE_CURVE_SAMPLE_COUNT, TONE_CURVE_RAMP_COLORS.len());
+        for (row, color) in ramp
+            .data
+            .chunks_exact_mut(TONE_CURVE_SAMPLE_COUNT * 3)
+            .zip(TONE_CURVE_RAMP_COLORS)
+        {
+            for (x, px) in row.chunks_exact_mut(3).enumerate() {
+                let t = x as f32 / (TONE_CURVE_SAMPLE_COUNT - 1) as f32;
+                let stimulus =
+                    (TONE_CURVE_LOG2_MIN + t * (TONE_CURVE_LOG2_MAX - TONE_CURVE_LOG2_MIN)).exp2();
+                px.copy_from_slice(&color.map(|c| c * stimulus));
+            }
+        }
+
+        // The current technique's curves, followed by the grey curves of pinned ones.
+        let curves_buffer = Buffer::new(
+            gl,
+            (TONE_CURVE_RAMP_COLORS.len() + TONE_CURVE_MAX_PINNED)
+                * TONE_CURVE_SAMPLE_COUNT
+                * std::mem::size_of::<f32>(),
+        );
+
+        Self {
+            ramp: Texture::new_2d(gl, &ramp),
+            draw_shader: shader_lib.add_shader("shaders/scope/tone_curve_draw.glsl"),
+            curves_buffer,
+            current: None,
+            pinned: Vec::new(),
+        }
+    }
+
+    /// Evaluates a technique at EV 0: `draw_technique` must render the given input texture
+    /// into the bound framebuffer.
+    pub fn evaluate(
+        &mut self,
+        gl: &gl::Gl,
+        technique_name: String,
+        draw_technique: impl FnOnce(&Texture),
+    ) {
+        let size = self.ramp.size;
+
+        // Float, so that values above 1 can be plotted.
+        let fbo = Fbo::with_format(gl, size, gl::RGBA32F);
+        fbo.bind(gl);
+        draw_technique(&self.ramp);
+
+        let mut pixels = vec![0f32; size[0] * size[1] * 4];
+        fbo.bind_read(gl);
+        unsafe {
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl.ReadPixels(
+                0,
+                0,
+                size[0] as _,
+                size[1] as _,
+                gl::RGBA,
+                gl::FLOAT,
+                pixels.as_mut_ptr() as *mut c_void,
+            );
+        }
+        fbo.unbind_read(gl);
+        fbo.destroy(gl);
+
+        // Rows come back bottom-up, while the ramp is top-down.
+        let luminance = pixels
+            .chunks_exact(size[0] * 4)
+            .rev()
+            .flat_map(|row| row.chunks_exact(4))
+            .map(|px| 0.2126 * px[0] + 0.7152 * px[1] + 0.0722 * px[2])
+            .collect();
+
+        self.current = Some(ToneCurves {
+            technique_name,
+            luminance,
+        });
+    }
+
+    /// Pins the grey curve of the last evaluated technique, or unpins it if it already is.
+    /// Returns whether it's now pinned, or `None` if nothing has been evaluated yet.
+    pub fn toggle_pin_current(&mut self) -> Option<bool> {
+        let current = self.current.as_ref()?;
+
+        if let Some(idx) = self
+            .pinned
+            .iter()
+            .position(|pinned| pinned.technique_name == current.technique_name)
+        {
+            self.pinned.remove(idx);
+            Some(false)
+        } else {
+            if self.pinned.len() == TONE_CURVE_MAX_PINNED {
+                self.pinned.remove(0);
+            }
+            self.pinned.push(current.clone());
+            Some(true)
+        }
+    }
+
+    pub fn draw(&self, gl: &gl::Gl, shader_lib: &ShaderLib, rect: OverlayRect) {
+        let current = match &self.current {
+            Some(current) => current,
+            None => return,
+        };
+
+        let curves: Vec<f32> = current
+            .luminance
+            .iter()
+            .chain(self.pinned.iter().flat_map(|pinned| pinned.grey()))
+            .copied()
+            .collect();
+        self.curves_buffer.write(gl, &curves);
+
+        if let Some(shader_program) = shader_lib.get_shader_gl_handle(&self.draw_shader) {
+            draw_overlay(gl, shader_program, rect, |shader_program| unsafe {
+                self.curves_buffer.bind_storage(gl, 0);
+
+                let loc = shader_lib.uniform_location(gl, shader_program, "pinned_count");
+                gl.Uniform1i(loc, self.pinned.len() as i32);
+            });
+        }
+    }
+}
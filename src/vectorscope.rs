@@ -0,0 +1,105 @@
+use crate::{
+    buffer::Buffer,
+    overlay::{draw_overlay, OverlayRect},
+    shader::{dispatch_compute, ShaderKey},
+    shader_lib::ShaderLib,
+    texture::Texture,
+};
+
+// Must match `VECTORSCOPE_RESOLUTION` in `shaders/scope/vectorscope_common.glsl`
+const VECTORSCOPE_RESOLUTION: usize = 256;
+
+pub const VECTORSCOPE_OVERLAY_SIZE: [i32; 2] = [320, 320];
+
+// Must match the `VECTORSCOPE_MODE_*` defines in `shaders/scope/vectorscope_common.glsl`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum VectorscopeMode {
+    YCbCr = 0,
+    Oklab = 1,
+}
+
+/// Chroma plot of the display output, either as BT.709 CbCr, or Oklab a/b.
+pub struct Vectorscope {
+    compute_shader: ShaderKey,
+    draw_shader: ShaderKey,
+    bins_buffer: Buffer,
+    pixel_count: f32,
+}
+
+impl Vectorscope {
+    pub fn new(shader_lib: &mut ShaderLib, gl: &gl::Gl) -> Self {
+        let compute_shader = shader_lib.add_compute_shader("shaders/scope/vectorscope.glsl");
+        let draw_shader = shader_lib.add_shader("shaders/scope/vectorscope_draw.glsl");
+
+        let bins_buffer = Buffer::new(
+            gl,
+            VECTORSCOPE_RESOLUTION * VECTORSCOPE_RESOLUTION * std::mem::size_of::<u32>(),
+        );
+
+        Self {
+            compute_shader,
+            draw_shader,
+            bins_buffer,
+            pixel_count: 1.0,
+        }
+    }
+
+    pub fn compute(
+        &mut self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        mode: VectorscopeMode,
+        output: &Texture,
+    ) {
+        let shader_program = match shader_lib.get_shader_gl_handle(&self.compute_shader) {
+            Some(shader_program) => shader_program,
+            None => return,
+        };
+
+        self.bins_buffer.clear(gl);
+        self.pixel_count = (output.size[0] * output.size[1]) as f32;
+
+        unsafe {
+            gl.UseProgram(shader_program);
+
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.BindTexture(gl::TEXTURE_2D, output.id);
+            let loc = shader_lib.uniform_location(gl, shader_program, "output_texture");
+            gl.Uniform1i(loc, 0);
+
+            let loc = shader_lib.uniform_location(gl, shader_program, "vectorscope_mode");
+            gl.Uniform1i(loc, mode as i32);
+
+            self.bins_buffer.bind_storage(gl, 0);
+
+            dispatch_compute(
+                gl,
+                shader_program,
+                [output.size[0] as u32, output.size[1] as u32, 1],
+            );
+
+            gl.UseProgram(0);
+            gl.MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+        }
+    }
+
+    pub fn draw(
+        &self,
+        gl: &gl::Gl,
+        shader_lib: &ShaderLib,
+        mode: VectorscopeMode,
+        rect: OverlayRect,
+    ) {
+        if let Some(shader_program) = shader_lib.get_shader_gl_handle(&self.draw_shader) {
+            draw_overlay(gl, shader_program, rect, |shader_program| unsafe {
+                self.bins_buffer.bind_storage(gl, 0);
+
+                let loc = shader_lib.uniform_location(gl, shader_program, "vectorscope_mode");
+                gl.Uniform1i(loc, mode as i32);
+
+                let loc = shader_lib.uniform_location(gl, shader_program, "pixel_count");
+                gl.Uniform1f(loc, self.pixel_count);
+            });
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{
+    buffer::Buffer,
+    overlay::{draw_overlay, OverlayRect},
+    shader::{dispatch_compute, ShaderKey},
+    shader_lib::ShaderLib,
+    texture::Texture,
+};
+
+// Must match `WAVEFORM_COLUMN_COUNT` and `WAVEFORM_LEVEL_COUNT` in `shaders/scope/waveform_common.glsl`
+const WAVEFORM_COLUMN_COUNT: usize = 256;
+const WAVEFORM_LEVEL_COUNT: usize = 256;
+
+// Luma, R, G, and B
+const WAVEFORM_CHANNEL_COUNT: usize = 4;
+
+pub const WAVEFORM_OVERLAY_SIZE: [i32; 2] = [512, 320];
+
+// Must match the `WAVEFORM_MODE_*` defines in `shaders/scope/waveform_draw.glsl`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WaveformMode {
+    Luma = 0,
+    RgbParade = 1,
+}
+
+/// Waveform monitor of the display output: code value vs horizontal image position.
+pub struct Waveform {
+    compute_shader: ShaderKey,
+    draw_shader: ShaderKey,
+    bins_buffer: Buffer,
+    pixels_per_column: f32,
+}
+
+impl Waveform {
+    pub fn new(shader_lib: &mut ShaderLib, gl: &gl::Gl) -> Self {
+        let compute_shader = shader_lib.add_compute_shader("shaders/scope/waveform.glsl");
+        let draw_shader = shader_lib.add_shader("shaders/scope/waveform_draw.glsl");
+
+        let bins_buffer = Buffer::new(
+            gl,
+            WAVEFORM_COLUMN_COUNT
+                * WAVEFORM_LEVEL_COUNT
+                * WAVEFORM_CHANNEL_COUNT
+                * std::mem::size_of::<u32>(),
+        );
+
+        Self {
+            compute_shader,
+            draw_shader,
+            bins_buffer,
+            pixels_per_column: 1.0,
+        }
+    }
+
+    pub fn compute(&mut self, gl: &gl::Gl, shader_lib: &ShaderLib, output: &Texture) {
+        let shader_program = match shader_lib.get_shader_gl_handle(&self.compute_shader) {
+            Some(shader_program) => shader_program,
+            None => return,
+        };
+
+        self.bins_buffer.clear(gl);
+        self.pixels_per_column =
+            (output.size[0] * output.size[1]) as f32 / WAVEFORM_COLUMN_COUNT as f32;
+
+        unsafe {
+            gl.UseProgram(shader_program);
+
+            gl.ActiveTexture(gl::TEXTURE0);
+            gl.BindTexture(gl::TEXTURE_2D, output.id);
+            let loc = shader_lib.uniform_location(gl, shader_program, "output_texture");
+            gl.Uniform1i(loc, 0);
+
+            self.bins_buffer.bind_storage(gl, 0);
+
+            dispatch_compute(
+                gl,
+                shader_program,
+                [output.size[0] as u32, output.size[1] as u32, 1],
+            );
+
+            gl.UseProgram(0);
+            gl.MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+        }
+    }
+
+    pub fn draw(&self, gl: &gl::Gl, shader_lib: &ShaderLib, mode: WaveformMode, rect: OverlayRect) {
+        if let Some(shader_program) = shader_lib.get_shader_gl_handle(&self.draw_shader) {
+            draw_overlay(gl, shader_program, rect, |shader_program| unsafe {
+                self.bins_buffer.bind_storage(gl, 0);
+
+                let loc = shader_lib.uniform_location(gl, shader_program, "waveform_mode");
+                gl.Uniform1i(loc, mode as i32);
+
+                let loc = shader_lib.uniform_location(gl, shader_program, "pixels_per_column");
+                gl.Uniform1f(loc, self.pixels_per_column);
+            });
+        }
+    }
+}